@@ -8,6 +8,7 @@ pub struct GamePhase {
 }
 
 impl GamePhase {
+    #[allow(clippy::erasing_op, clippy::identity_op)]
     pub fn new(board: &Board) -> Self {
         let mid_weight = (
             // Sum pieces to get phase
@@ -1,7 +1,7 @@
 use chess::{Color, Piece};
 
-use crate::evaluate::Score;
 use crate::evaluate::pesto::gamephase::GamePhase;
+use crate::evaluate::Score;
 
 #[derive(Clone, Copy, Debug)]
 pub struct RawPhasedScore {
@@ -25,6 +25,10 @@ impl RawPhasedScore {
         }
     }
 
+    pub const fn collapse(self, phase: &GamePhase) -> Score {
+        phase.weight(self.mid_game, self.end_game)
+    }
+
     pub const fn colorize(self, color: Color) -> PhasedScore {
         match color {
             Color::White => PhasedScore {
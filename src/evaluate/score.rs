@@ -1,3 +1,5 @@
+use crate::search::Depth;
+
 pub type Score = i64;
 
 // By defining a Centipawn as this number,
@@ -9,35 +11,75 @@ pub const MATE: Score = Score::MAX / 16;
 pub const MATE_MOVE: Score = CENTIPAWN;
 pub const MATE_CUTOFF: Score = MATE - 1024 * MATE_MOVE;
 
+// A win the tablebases promise, which is worth less than any mate the search can see.
+pub const TB_WIN: Score = 1000 * 100 * CENTIPAWN;
+pub const TB_WIN_CUTOFF: Score = TB_WIN - 1024 * MATE_MOVE;
+
 pub const DRAW: Score = 0;
 
-pub fn score_mark(score: Score) -> Score {
-    // This is how we keep track of how many moves we are from mate.
-    // Every time we call this function, we take a bit from the score,
-    // to indicate that it takes an extra move to get here.
-    if score >= MATE_CUTOFF {
-        score - MATE_MOVE
-    } else if score <= -MATE_CUTOFF {
-        score + MATE_MOVE
-    } else {
+// Mate scores count the plies from the root of the search to the mate,
+// so that a shorter mate is always worth more than a longer one.
+pub fn mate_in(ply: Depth) -> Score {
+    MATE - ply * MATE_MOVE
+}
+
+pub fn mated_in(ply: Depth) -> Score {
+    -MATE + ply * MATE_MOVE
+}
+
+// Tablebase wins count the plies to the position that was found in the tables,
+// so that the search heads for the nearest one.
+pub fn tb_win_in(ply: Depth) -> Score {
+    TB_WIN - ply * MATE_MOVE
+}
+
+pub fn tb_loss_in(ply: Depth) -> Score {
+    -TB_WIN + ply * MATE_MOVE
+}
+
+fn is_ply_counted(score: Score) -> bool {
+    score.abs() >= MATE_CUTOFF || (TB_WIN_CUTOFF..=TB_WIN).contains(&score.abs())
+}
+
+/// Counts a mate or tablebase score from the node at `ply` instead of from the root.
+pub fn score_to_node(score: Score, ply: Depth) -> Score {
+    if !is_ply_counted(score) {
         score
+    } else if score > 0 {
+        score + ply * MATE_MOVE
+    } else {
+        score - ply * MATE_MOVE
     }
 }
 
+/// Counts a mate or tablebase score from the root again, for a node at `ply`.
+pub fn score_from_node(score: Score, ply: Depth) -> Score {
+    score_to_node(score, -ply)
+}
+
 pub fn score_to_cp(score: Score) -> Score {
     score / (CENTIPAWN)
 }
 
-pub fn score_to_str(score: Score) -> String {
-    let side = if score < 0 { "-" } else { "" };
-    let score = score.abs();
+pub fn score_to_mate(score: Score) -> Option<Score> {
+    // Converts a mate score into the number of moves until mate,
+    // which is negative when we are the side getting mated.
+    if score.abs() >= MATE_CUTOFF {
+        // The side that mates moves on the odd plies, the side that gets mated on the even ones.
+        let plies = (MATE - score.abs() + MATE_MOVE / 2) / MATE_MOVE;
+        let moves = (plies + 1) / 2;
 
-    if score >= MATE_CUTOFF {
-        let diff = (MATE - score + MATE_MOVE / 2) / MATE_MOVE;
-        let moves = (diff + 1) / 2;
+        Some(score.signum() * moves)
+    } else {
+        None
+    }
+}
 
-        format!("mate {}{}", side, moves)
+pub fn score_to_str(score: Score) -> String {
+    if let Some(moves) = score_to_mate(score) {
+        format!("mate {}", moves)
     } else {
-        format!("cp {}{}", side, score_to_cp(score))
+        let side = if score < 0 { "-" } else { "" };
+        format!("cp {}{}", side, score_to_cp(score.abs()))
     }
 }
@@ -1,5 +1,6 @@
 mod evaluate;
 mod search;
+mod syzygy;
 mod tests;
 mod transposition;
 mod uci;
@@ -1,6 +1,6 @@
 use std::ops;
 
-use crate::evaluate::{Score, CENTIPAWN, MATE};
+use crate::evaluate::{Score, CENTIPAWN, MATE, MATE_CUTOFF};
 use crate::search::Depth;
 use crate::transposition::best_moves::BestMoves;
 use crate::transposition::table_entry::TTableEntry;
@@ -16,6 +16,8 @@ pub struct AlphaBeta {
     pub alpha: Score,
     pub beta: Score,
     pub ply: Depth,
+    // Plies of depth added to this line by extensions.
+    pub extensions: Depth,
 }
 
 impl AlphaBeta {
@@ -24,14 +26,68 @@ impl AlphaBeta {
             alpha: -MATE - CENTIPAWN,
             beta: MATE + CENTIPAWN,
             ply: 0,
+            extensions: 0,
         }
     }
 
+    /// A window of `delta` around a guess of the score. It falls back
+    /// to the full window once it reaches into the mate scores.
+    pub fn aspiration(guess: Score, delta: Score) -> Self {
+        Self::new().bounded(guess - delta, guess + delta)
+    }
+
+    fn bounded(&self, alpha: Score, beta: Score) -> Self {
+        let full = Self::new();
+        Self {
+            alpha: if alpha <= -MATE_CUTOFF {
+                full.alpha
+            } else {
+                alpha
+            },
+            beta: if beta >= MATE_CUTOFF { full.beta } else { beta },
+            ply: self.ply,
+            extensions: self.extensions,
+        }
+    }
+
+    /// The full window, at the same ply of the same line.
+    pub fn full_width(&self) -> Self {
+        self.bounded(-MATE_CUTOFF, MATE_CUTOFF)
+    }
+
+    /// Widens the window below after the search failed low, doubling its span.
+    pub fn widen_alpha(&self) -> Self {
+        self.bounded(self.alpha - self.span(), self.beta)
+    }
+
+    /// Widens the window above after the search failed high, doubling its span.
+    pub fn widen_beta(&self) -> Self {
+        self.bounded(self.alpha, self.beta + self.span())
+    }
+
     pub fn null_move(&self) -> Self {
         Self {
             alpha: 0 - self.beta,
             beta: 1 - self.beta,
             ply: self.ply + 1,
+            extensions: self.extensions,
+        }
+    }
+
+    /// A null window just below `beta`, which only tells whether a score reaches it.
+    pub fn scout(&self, beta: Score) -> Self {
+        Self {
+            alpha: beta - 1,
+            beta,
+            ..*self
+        }
+    }
+
+    /// The same window, for a line that was extended by `extension` plies.
+    pub fn extended(&self, extension: Depth) -> Self {
+        Self {
+            extensions: self.extensions + extension,
+            ..*self
         }
     }
 
@@ -76,6 +132,7 @@ impl ops::Neg for AlphaBeta {
             alpha: -self.beta,
             beta: -self.alpha,
             ply: self.ply + 1,
+            extensions: self.extensions,
         }
     }
 }
@@ -1,112 +1,206 @@
-use chess::{BitBoard, Board, ChessMove, EMPTY};
+use chess::{BitBoard, Board, BoardStatus, ChessMove, Piece, ALL_COLORS, EMPTY};
 
-const REPETITION_SEARCH_DEPTH: usize = 9;
+// Plies without a capture or a pawn move before the game is drawn.
+const FIFTY_MOVE_PLIES: usize = 100;
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub enum BoardChain<'a> {
-    First(Board, usize),
-    Linked(&'a BoardChain<'a>, Board, usize),
-    OwnedLink(Box<BoardChain<'a>>, Board, usize),
+#[derive(Clone, Debug)]
+enum Previous<'a> {
+    None,
+    Borrowed(&'a BoardChain<'a>),
+    Owned(Box<BoardChain<'a>>),
+}
+
+/// The boards of the game so far, followed by the boards of the line being searched.
+///
+/// Every link keeps the Zobrist key of its board, so repetitions are found by
+/// comparing keys back to the last move that could not be undone.
+#[derive(Clone, Debug)]
+pub struct BoardChain<'a> {
+    previous: Previous<'a>,
+    board: Board,
+    key: u64,
+    // The move that led to this board, or none after a null move.
+    movement: Option<ChessMove>,
+    // Plies since the last capture or pawn move, for the fifty-move rule.
+    halfmove_clock: usize,
+    // Plies since the last move that no repetition can reach past.
+    reversible_plies: usize,
+    // How often this board has occurred, counting itself.
+    repetitions: usize,
+    // Whether this board repeats a board from inside the search tree.
+    search_repetition: bool,
+    in_search: bool,
 }
 
 impl BoardChain<'static> {
     pub fn new(board: Board) -> Self {
-        BoardChain::First(board, 1)
+        Self::with_halfmove_clock(board, 0)
     }
 
-    pub fn take_board(self, board: Board) -> BoardChain<'static> {
-        let repetitions = self.get_repetitions(&board, REPETITION_SEARCH_DEPTH);
-        match self {
-            BoardChain::First(_, _) => BoardChain::OwnedLink(Box::new(self), board, repetitions),
-            BoardChain::Linked(_, _, _) => panic!("Cannot take board from linked chain"),
-            BoardChain::OwnedLink(_, _, _) => {
-                BoardChain::OwnedLink(Box::new(self), board, repetitions)
-            }
+    pub fn with_halfmove_clock(board: Board, halfmove_clock: usize) -> Self {
+        BoardChain {
+            previous: Previous::None,
+            board,
+            key: board.get_hash(),
+            movement: None,
+            halfmove_clock,
+            reversible_plies: 0,
+            repetitions: 1,
+            search_repetition: false,
+            in_search: false,
         }
     }
 
     pub fn take_move(self, movement: ChessMove) -> BoardChain<'static> {
-        let new_board = self.last().make_move_new(movement);
-        let capture = self.last().combined() & BitBoard::from_square(movement.get_dest());
-        if capture != EMPTY {
-            BoardChain::new(new_board)
-        } else {
-            self.take_board(new_board)
-        }
+        let (board, halfmove_clock, reversible_plies) = self.next(movement);
+        BoardChain::link(
+            Previous::Owned(Box::new(self)),
+            board,
+            Some(movement),
+            halfmove_clock,
+            reversible_plies,
+            false,
+        )
     }
 }
 
 impl<'a> BoardChain<'a> {
-    pub fn get_repetitions(&self, test_board: &Board, depth: usize) -> usize {
-        if depth <= 0 {
-            return 1;
-        } else {
-            match self {
-                BoardChain::First(board, repetitions) => {
-                    if board == test_board {
-                        *repetitions + 1
-                    } else {
-                        1
-                    }
-                }
-                BoardChain::Linked(previous, board, repetitions) => {
-                    if board == test_board {
-                        *repetitions + 1
-                    } else {
-                        previous.get_repetitions(test_board, depth - 1)
-                    }
-                }
-                BoardChain::OwnedLink(previous, board, repetitions) => {
-                    if board == test_board {
-                        *repetitions + 1
-                    } else {
-                        previous.get_repetitions(test_board, depth - 1)
-                    }
-                }
-            }
+    fn link(
+        previous: Previous<'a>,
+        board: Board,
+        movement: Option<ChessMove>,
+        halfmove_clock: usize,
+        reversible_plies: usize,
+        in_search: bool,
+    ) -> BoardChain<'a> {
+        let mut chain = BoardChain {
+            previous,
+            board,
+            key: board.get_hash(),
+            movement,
+            halfmove_clock,
+            reversible_plies,
+            repetitions: 1,
+            search_repetition: false,
+            in_search,
+        };
+
+        // Only the latest earlier occurrence matters, as it already counts the ones before it.
+        if let Some((repetitions, earlier_in_search)) = chain
+            .find_repetition()
+            .map(|earlier| (earlier.repetitions, earlier.in_search))
+        {
+            chain.repetitions = repetitions + 1;
+            chain.search_repetition = in_search && earlier_in_search;
         }
+
+        chain
     }
 
-    pub fn with_board(&self, board: Board) -> BoardChain {
-        let repetitions = self.get_repetitions(&board, REPETITION_SEARCH_DEPTH);
-        BoardChain::Linked(self, board, repetitions)
+    fn previous(&self) -> Option<&BoardChain<'a>> {
+        match &self.previous {
+            Previous::None => None,
+            Previous::Borrowed(previous) => Some(previous),
+            Previous::Owned(previous) => Some(previous),
+        }
     }
 
-    pub fn with_move(&self, movement: ChessMove) -> BoardChain {
-        let new_board = self.last().make_move_new(movement);
-        let capture = self.last().combined() & BitBoard::from_square(movement.get_dest());
-        if capture != EMPTY {
-            BoardChain::new(new_board)
-        } else {
-            self.with_board(new_board)
+    fn find_repetition(&self) -> Option<&BoardChain<'a>> {
+        let mut link = self.previous();
+
+        // The same side has to be on move, so only every other ply can repeat.
+        for plies in 1..=self.reversible_plies {
+            let earlier = link?;
+            if plies % 2 == 0 && earlier.key == self.key {
+                return Some(earlier);
+            }
+
+            link = earlier.previous();
         }
+
+        None
     }
 
-    pub fn with_null_move(&self) -> Option<BoardChain> {
-        if let Some(null_board) = self.last().null_move() {
-            Some(self.with_board(null_board))
+    fn next(&self, movement: ChessMove) -> (Board, usize, usize) {
+        let board = self.board.make_move_new(movement);
+
+        let capture = self.board.combined() & BitBoard::from_square(movement.get_dest()) != EMPTY;
+        let pawn_move = self.board.piece_on(movement.get_source()) == Some(Piece::Pawn);
+        let castle_rights_lost = ALL_COLORS
+            .iter()
+            .any(|&color| self.board.castle_rights(color) != board.castle_rights(color));
+
+        let halfmove_clock = if capture || pawn_move {
+            0
         } else {
-            None
-        }
+            self.halfmove_clock + 1
+        };
+
+        let reversible_plies = if capture || pawn_move || castle_rights_lost {
+            0
+        } else {
+            self.reversible_plies + 1
+        };
+
+        (board, halfmove_clock, reversible_plies)
+    }
+
+    pub fn with_move(&self, movement: ChessMove) -> BoardChain<'_> {
+        let (board, halfmove_clock, reversible_plies) = self.next(movement);
+        BoardChain::link(
+            Previous::Borrowed(self),
+            board,
+            Some(movement),
+            halfmove_clock,
+            reversible_plies,
+            true,
+        )
+    }
+
+    pub fn with_null_move(&self) -> Option<BoardChain<'_>> {
+        // Passing is not a legal move, so no repetition may reach past it.
+        self.board.null_move().map(|null_board| {
+            BoardChain::link(
+                Previous::Borrowed(self),
+                null_board,
+                None,
+                self.halfmove_clock + 1,
+                0,
+                true,
+            )
+        })
     }
 
     pub fn last(&self) -> &Board {
-        match self {
-            BoardChain::First(board, _) => board,
-            BoardChain::Linked(_, board, _) => board,
-            BoardChain::OwnedLink(_, board, _) => board,
-        }
+        &self.board
+    }
+
+    pub fn last_move(&self) -> Option<ChessMove> {
+        self.movement
+    }
+
+    /// Plies since the last capture or pawn move.
+    pub fn halfmove_clock(&self) -> usize {
+        self.halfmove_clock
+    }
+
+    /// Whether this board was reached by passing, rather than by a move.
+    pub fn after_null_move(&self) -> bool {
+        self.movement.is_none() && self.previous().is_some()
     }
 
+    #[cfg(test)]
     pub fn repetitions(&self) -> usize {
-        match self {
-            BoardChain::First(_, repetitions) => *repetitions,
-            BoardChain::Linked(_, _, repetitions) => *repetitions,
-            BoardChain::OwnedLink(_, _, repetitions) => *repetitions,
-        }
+        self.repetitions
     }
 
+    /// Repeating a board from inside the search tree is scored as a draw right away,
+    /// since either side could repeat it again, while boards from the game need three.
     pub fn is_draw(&self) -> bool {
-        self.repetitions() >= 3
+        // A checkmate on the last ply before the fifty-move rule still counts.
+        self.search_repetition
+            || self.repetitions >= 3
+            || (self.halfmove_clock >= FIFTY_MOVE_PLIES
+                && self.board.status() != BoardStatus::Checkmate)
     }
 }
@@ -1,39 +1,113 @@
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::evaluate::{score_to_mate, Score};
 
 use super::Depth;
 
+// Marks a clock that has not started yet, because the engine is pondering.
+const PONDERING: u64 = u64::MAX;
+
 pub struct Deadline {
-    deadline: Option<Instant>,
+    created: Instant,
+    timeout: Option<Duration>,
+    optimum: Option<Duration>,
+    clock_start: AtomicU64,
     trigger: AtomicBool,
     max_depth: Option<Depth>,
+    max_nodes: Option<usize>,
+    mate: Option<Score>,
 }
 
 impl Deadline {
     pub fn none() -> Deadline {
         Deadline {
-            deadline: None,
+            created: Instant::now(),
+            timeout: None,
+            optimum: None,
+            clock_start: AtomicU64::new(0),
             trigger: AtomicBool::new(false),
             max_depth: None,
+            max_nodes: None,
+            mate: None,
         }
     }
 
     pub fn depth(depth: Depth) -> Deadline {
         Deadline {
-            deadline: None,
-            trigger: AtomicBool::new(false),
             max_depth: Some(depth),
+            ..Deadline::none()
         }
     }
 
-    pub fn timeout(duration: std::time::Duration) -> Deadline {
+    pub fn timeout(duration: Duration) -> Deadline {
         Deadline {
-            deadline: Some(Instant::now() + duration),
-            trigger: AtomicBool::new(false),
-            max_depth: None,
+            timeout: Some(duration),
+            ..Deadline::none()
+        }
+    }
+
+    /// Searches for about `optimum` when the search is stable,
+    /// but never for longer than `maximum`.
+    pub fn time_limit(optimum: Duration, maximum: Duration) -> Deadline {
+        Deadline {
+            timeout: Some(maximum),
+            optimum: Some(optimum.min(maximum)),
+            ..Deadline::none()
         }
     }
 
+    pub fn nodes(nodes: usize) -> Deadline {
+        Deadline {
+            max_nodes: Some(nodes),
+            ..Deadline::none()
+        }
+    }
+
+    /// Searches until a mate in at most `moves` is found, or until the search is stopped.
+    /// Pruning can hide a mate for many plies past its length, so no depth is deep enough.
+    pub fn mate(moves: Score) -> Deadline {
+        Deadline {
+            mate: Some(moves),
+            ..Deadline::none()
+        }
+    }
+
+    /// The deadline of the helper threads of a search. They stop once they are
+    /// triggered, or once all threads together have searched the node budget.
+    pub fn for_helpers(&self) -> Deadline {
+        Deadline {
+            max_nodes: self.max_nodes,
+            ..Deadline::none()
+        }
+    }
+
+    #[cfg(test)]
+    pub fn optimum(&self) -> Option<Duration> {
+        self.optimum
+    }
+
+    #[cfg(test)]
+    pub fn maximum(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn ponder(self) -> Deadline {
+        // The clock only starts once the opponent plays the expected move,
+        // until then the search runs as if it were infinite.
+        self.clock_start.store(PONDERING, Ordering::Relaxed);
+        self
+    }
+
+    pub fn ponder_hit(&self) {
+        let elapsed = self.created.elapsed().as_nanos() as u64;
+        self.clock_start.store(elapsed, Ordering::Relaxed);
+    }
+
+    pub fn is_pondering(&self) -> bool {
+        self.clock_start.load(Ordering::Relaxed) == PONDERING
+    }
+
     pub fn check_depth(&self, depth: Depth) -> bool {
         if let Some(max_depth) = self.max_depth {
             depth < max_depth
@@ -42,9 +116,46 @@ impl Deadline {
         }
     }
 
+    pub fn check_nodes(&self, nodes: usize) -> bool {
+        if let Some(max_nodes) = self.max_nodes {
+            nodes < max_nodes
+        } else {
+            true
+        }
+    }
+
+    pub fn check_score(&self, score: Score) -> bool {
+        // A mate search is done once it has proven a mate
+        // for us in at most the requested number of moves.
+        match (self.mate, score_to_mate(score)) {
+            (Some(mate), Some(moves)) => !(0 < moves && moves <= mate),
+            _ => true,
+        }
+    }
+
+    fn elapsed(&self) -> Option<Duration> {
+        match self.clock_start.load(Ordering::Relaxed) {
+            PONDERING => None,
+            clock_start => Some(
+                self.created
+                    .elapsed()
+                    .saturating_sub(Duration::from_nanos(clock_start)),
+            ),
+        }
+    }
+
+    pub fn check_time(&self, scale: f64) -> bool {
+        // Another iteration is only started while we are within
+        // the optimum time, scaled by how unsettled the search is.
+        match (self.optimum, self.elapsed()) {
+            (Some(optimum), Some(elapsed)) => elapsed < optimum.mul_f64(scale),
+            _ => true,
+        }
+    }
+
     pub fn passed(&self) -> bool {
-        if let Some(deadline) = self.deadline {
-            if Instant::now() >= deadline {
+        if let (Some(timeout), Some(elapsed)) = (self.timeout, self.elapsed()) {
+            if elapsed >= timeout {
                 return true;
             }
         }
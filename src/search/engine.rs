@@ -1,70 +1,303 @@
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
 use std::time::Instant;
 
-use chess::{Board, ChessMove, EMPTY};
+use chess::{Board, ChessMove, Color, Piece, Rank, EMPTY};
 
-use crate::evaluate::{evaluate, score_mark, Score, DRAW, MATE, MATE_CUTOFF};
+use crate::evaluate::pesto::gamephase::GamePhase;
+use crate::evaluate::{
+    evaluate, mate_in, mated_in, tb_loss_in, tb_win_in, Score, CENTIPAWN, DRAW, MATE_CUTOFF,
+};
 
 use crate::search::alpha_beta::{AlphaBeta, NegaMaxResult::*};
 use crate::search::board_chain::BoardChain;
 use crate::search::deadline::Deadline;
+use crate::search::move_history::{is_quiet, MoveHistory};
 use crate::search::movegen::OrderedMoveGen;
-use crate::search::Depth;
+use crate::search::opponent_model::{OpponentModel, OpponentModelKind, OPPONENT_SHALLOW_DEPTH};
+use crate::search::pruning::Pruning;
+use crate::search::pv_array::PVArray;
+use crate::search::see::capture_value;
+use crate::search::{Depth, DEPTH_LEAF};
+
+use crate::syzygy::probe::Tablebases;
+use crate::syzygy::Wdl;
 
 use crate::transposition::best_moves::BestMoves;
-use crate::transposition::pv_line::PVLine;
 use crate::transposition::rated_move::RatedMove;
 use crate::transposition::table::{TTable, TTableSample};
 use crate::transposition::table_entry::TTableEntry;
 
-const DEFAULT_TABLE_SIZE: usize = 1000 * 1000 * 1000;
+pub const DEFAULT_TABLE_SIZE: usize = 1024 * 1024 * 1024;
+
+// The span on either side of the previous score that the next iteration searches first.
+const ASPIRATION_WINDOW: Score = 25 * CENTIPAWN;
+
+// Shallow iterations are cheap and their scores are unreliable guesses.
+const ASPIRATION_DEPTH: Depth = 4;
+
+// How far past its static gain a capture has to be able to swing the score to be searched.
+const DELTA_MARGIN: Score = 200 * CENTIPAWN;
+
+// The most plies a quiescence search follows before it settles for the evaluation.
+const QSEARCH_DEPTH: Depth = 16;
+
+// Whether the first ply of the quiescence search also looks at quiet checks.
+const QSEARCH_CHECKS: bool = true;
+
+// The most plies that extensions may add to a single line.
+const EXTENSION_BUDGET: Depth = 16;
+
+// Shallower nodes are not worth the extra search that proves a move singular.
+const SINGULAR_DEPTH: Depth = 8;
+
+// Per ply of depth, how far every other move has to stay below the table move.
+const SINGULAR_MARGIN: Score = 3 * CENTIPAWN;
+
+// Whether a pawn that moves up to the rank before promotion is extended.
+const PAWN_PUSH_EXTENSIONS: bool = true;
+
+// How many nodes a thread counts on its own before adding them to the shared total.
+const NODE_BATCH: usize = 256;
+
+// Null move cutoffs this deep are checked by a shallower search that may not pass.
+const NULL_VERIFICATION_DEPTH: Depth = 8;
+
+fn is_pawn_push_to_seventh(board: &Board, movement: ChessMove) -> bool {
+    let seventh = match board.side_to_move() {
+        Color::White => Rank::Seventh,
+        Color::Black => Rank::Second,
+    };
+
+    board.piece_on(movement.get_source()) == Some(Piece::Pawn)
+        && movement.get_dest().get_rank() == seventh
+}
 
 pub struct Engine {
-    pub table: TTable,
-    pub opponent_engine: Option<Box<Engine>>,
+    pub table: Arc<TTable>,
+    helpers: Vec<Engine>,
+    search_moves: Vec<ChessMove>,
+    excluded_moves: Vec<ChessMove>,
+    multi_pv: usize,
+    lines: Vec<TTableEntry>,
+    line_pvs: Vec<Vec<ChessMove>>,
+    research: Option<(Depth, AlphaBeta)>,
+    history: MoveHistory,
+    pv: PVArray,
+    pruning: Pruning,
+    opponent_model: OpponentModel,
+    tablebases: Arc<Tablebases>,
+    thread_id: usize,
     nodes: usize,
+    tb_hits: usize,
+    // The nodes of the main thread and its helpers, as far as each has reported them.
+    shared_nodes: Arc<AtomicUsize>,
+    pv_nodes: usize,
+    re_searches: usize,
+    seldepth: Depth,
+    // The first ply that may pass again, while a null move cutoff is being verified.
+    null_move_ply: Depth,
 }
 
 impl Engine {
     pub fn new() -> Engine {
+        Self::with_table_size(DEFAULT_TABLE_SIZE)
+    }
+
+    pub fn with_table_size(table_size: usize) -> Engine {
         Engine {
-            table: TTable::new(DEFAULT_TABLE_SIZE),
-            opponent_engine: Some(Box::new(Engine {
-                table: TTable::new(DEFAULT_TABLE_SIZE),
-                opponent_engine: None,
-                nodes: 0,
-            })),
+            table: Arc::new(TTable::new(table_size)),
+            helpers: Vec::new(),
+            search_moves: Vec::new(),
+            excluded_moves: Vec::new(),
+            multi_pv: 1,
+            lines: Vec::new(),
+            line_pvs: Vec::new(),
+            research: None,
+            history: MoveHistory::new(),
+            pv: PVArray::new(),
+            pruning: Pruning::DEFAULT,
+            opponent_model: OpponentModel::DEFAULT,
+            tablebases: Arc::new(Tablebases::default()),
+            thread_id: 0,
             nodes: 0,
+            tb_hits: 0,
+            shared_nodes: Arc::new(AtomicUsize::new(0)),
+            pv_nodes: 0,
+            re_searches: 0,
+            seldepth: 0,
+            null_move_ply: 0,
         }
     }
 
-    pub fn ab_qsearch(board: &Board, mut window: AlphaBeta) -> Score {
-        let (mut best, movegen) = {
-            if *board.checkers() == EMPTY {
-                let score = evaluate(&board);
-                if let Pruned = window.negamax(score) {
-                    return score_mark(score);
-                }
+    fn new_helper(&self, thread_id: usize) -> Engine {
+        Engine {
+            table: Arc::clone(&self.table),
+            helpers: Vec::new(),
+            search_moves: Vec::new(),
+            excluded_moves: Vec::new(),
+            multi_pv: 1,
+            lines: Vec::new(),
+            line_pvs: Vec::new(),
+            research: None,
+            history: MoveHistory::new(),
+            pv: PVArray::new(),
+            pruning: self.pruning,
+            opponent_model: self.opponent_model,
+            tablebases: Arc::clone(&self.tablebases),
+            thread_id,
+            nodes: 0,
+            tb_hits: 0,
+            shared_nodes: Arc::clone(&self.shared_nodes),
+            pv_nodes: 0,
+            re_searches: 0,
+            seldepth: 0,
+            null_move_ply: 0,
+        }
+    }
 
-                (
-                    score,
-                    OrderedMoveGen::quiescence_search(&board, BestMoves::new()),
-                )
-            } else {
-                (-MATE, OrderedMoveGen::full_search(&board, BestMoves::new()))
+    pub fn set_search_moves(&mut self, search_moves: &[ChessMove]) {
+        // An empty list of search moves searches every move at the root.
+        self.search_moves = search_moves.to_vec();
+
+        for helper in self.helpers.iter_mut() {
+            helper.set_search_moves(search_moves);
+        }
+    }
+
+    pub fn set_multi_pv(&mut self, multi_pv: usize) {
+        self.multi_pv = multi_pv.max(1);
+    }
+
+    pub fn set_pruning(&mut self, pruning: Pruning) {
+        self.pruning = pruning;
+
+        for helper in self.helpers.iter_mut() {
+            helper.set_pruning(pruning);
+        }
+    }
+
+    pub fn set_opponent_model(&mut self, opponent_model: OpponentModel) {
+        self.opponent_model = opponent_model;
+
+        for helper in self.helpers.iter_mut() {
+            helper.set_opponent_model(opponent_model);
+        }
+    }
+
+    pub fn set_tablebases(&mut self, tablebases: Arc<Tablebases>) {
+        for helper in self.helpers.iter_mut() {
+            helper.set_tablebases(Arc::clone(&tablebases));
+        }
+
+        self.tablebases = tablebases;
+    }
+
+    /// Limits the root to the moves that keep the best result in the tablebases,
+    /// which for a win are the ones that get to the next capture or pawn move the fastest.
+    /// The search could not tell those moves apart, and would shuffle instead of converting.
+    pub fn probe_root(&mut self, history: &BoardChain) {
+        let Some(tablebase_moves) = self.tablebases.root_moves(history) else {
+            return;
+        };
+
+        self.tb_hits += tablebase_moves.len();
+        let search_moves: Vec<ChessMove> = tablebase_moves
+            .into_iter()
+            .filter(|&movement| self.is_search_move(Some(movement)))
+            .collect();
+
+        if !search_moves.is_empty() {
+            self.set_search_moves(&search_moves);
+        }
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.helpers = (1..threads.max(1))
+            .map(|thread_id| self.new_helper(thread_id))
+            .collect();
+    }
+
+    pub fn ab_qsearch(&mut self, board: &Board, mut window: AlphaBeta, qply: Depth) -> Score {
+        // This node was counted by whoever searched it, so only its children are counted here.
+        let original_window = window;
+        let check = *board.checkers() != EMPTY;
+        self.seldepth = self.seldepth.max(window.ply);
+
+        // Check evasions can go on for long, so deep lines end at the evaluation.
+        if qply >= QSEARCH_DEPTH {
+            return evaluate(board);
+        }
+
+        // Transposition Table Lookup
+        let pv = match self.table.sample::<false>(board, &window, DEPTH_LEAF) {
+            TTableSample::Score(entry) => return entry.score(),
+            TTableSample::Moves(moves) if check => moves,
+            TTableSample::Moves(moves) => moves.filtered(|mv| !is_quiet(board, mv)),
+            TTableSample::None => BestMoves::new(),
+        };
+
+        // Stand Pat
+        let stand_pat = if check { None } else { Some(evaluate(board)) };
+        if let Some(score) = stand_pat {
+            if let Pruned = window.negamax(score) {
+                self.table
+                    .update_leaf(board, window.ply, TTableEntry::NullCut(DEPTH_LEAF, score));
+                return score;
             }
+        }
+
+        let movegen = if check {
+            OrderedMoveGen::full_search(board, pv)
+        } else if QSEARCH_CHECKS && qply == 0 {
+            OrderedMoveGen::quiescence_checks(board, pv)
+        } else {
+            OrderedMoveGen::quiescence_search(board, pv)
         };
 
+        let phase = GamePhase::new(board);
+        let mut moves = BestMoves::new();
         for movement in movegen {
+            // Delta Pruning
+            if let Some(score) = stand_pat {
+                if !is_quiet(board, movement)
+                    && score + capture_value(board, movement, &phase) + DELTA_MARGIN <= window.alpha
+                {
+                    continue;
+                }
+            }
+
+            self.count_node();
             let new_board = board.make_move_new(movement);
-            let eval = -Self::ab_qsearch(&new_board, -window);
+            let eval = -self.ab_qsearch(&new_board, -window, qply + 1);
 
-            best = best.max(eval);
+            moves.push(RatedMove::new(eval, movement));
             if let Pruned = window.negamax(eval) {
                 break;
             }
         }
 
-        score_mark(best)
+        // Store and Return Results
+        // A stand pat that no capture improves on is exact, as long as it is inside
+        // the window, since the captures that were pruned could only reach alpha.
+        let best = moves.score().max(stand_pat.unwrap_or(mated_in(window.ply)));
+        let entry = if check && moves.is_none() {
+            Some(TTableEntry::Edge(mated_in(window.ply)))
+        } else if !moves.is_none() && best <= moves.score() {
+            Some(original_window.new_table_entry(DEPTH_LEAF, moves))
+        } else if original_window.alpha < best {
+            Some(TTableEntry::Leaf(best))
+        } else {
+            None
+        };
+
+        if let Some(entry) = entry {
+            self.table.update_leaf(board, window.ply, entry);
+        }
+
+        best
     }
 
     fn ab_search<const PV: bool>(
@@ -74,103 +307,275 @@ impl Engine {
         mut window: AlphaBeta,
         deadline: &Deadline,
     ) -> Result<TTableEntry, ()> {
-        let original_window = window;
-        self.nodes += 1;
+        self.count_node();
+        self.seldepth = self.seldepth.max(window.ply);
+        if PV {
+            self.pv_nodes += 1;
+            self.pv.clear(window.ply);
+        }
 
-        // Check for Time Exceeded
-        if deadline.passed() {
+        // Check for Time or Node Budget Exceeded
+        if deadline.passed() || !deadline.check_nodes(self.shared_node_count()) {
             return Err(());
         }
 
         // Draw Detection and Handling
-        if board.is_draw() {
-            return TTableEntry::Edge(if window.opponent() { -DRAW } else { DRAW }).mark();
+        // The root is searched anyway, since a legal move has to be played from it.
+        if window.ply > 0 && board.is_draw() {
+            return Ok(TTableEntry::Edge(if window.opponent() {
+                -DRAW
+            } else {
+                DRAW
+            }));
+        }
+
+        // Mate Distance Pruning
+        // No score here can beat mating on the next move, or fall below being mated now.
+        if window.ply > 0 {
+            window.alpha = window.alpha.max(mated_in(window.ply));
+            window.beta = window.beta.min(mate_in(window.ply + 1));
+            if window.alpha >= window.beta {
+                return Ok(TTableEntry::Leaf(window.alpha));
+            }
         }
 
+        // Tablebase Probing
+        // The tables know nothing about the fifty-move rule, so they are only trusted
+        // right after a capture or a pawn move, when its count starts over.
+        if window.ply > 0 && board.halfmove_clock() == 0 {
+            if let Some(wdl) = self.tablebases.probe_wdl(board.last()) {
+                self.tb_hits += 1;
+
+                let score = match wdl {
+                    Wdl::Win => tb_win_in(window.ply),
+                    Wdl::Loss => tb_loss_in(window.ply),
+                    _ if window.opponent() => -DRAW,
+                    _ => DRAW,
+                };
+
+                let entry = TTableEntry::Edge(score);
+                self.table.update::<PV>(board.last(), window.ply, entry);
+                return Ok(entry);
+            }
+        }
+
+        let original_window = window;
+
         // Quiescence Search
         if depth <= 0 {
-            let eval = Self::ab_qsearch(board.last(), window);
+            let eval = self.ab_qsearch(board.last(), window, 0);
             let entry = TTableEntry::Leaf(eval);
-            return entry.mark();
-        }
-
-        // Opponent Modeling to
-        if window.opponent() && depth > 7 {
-            if let Some(opponent_engine) = &mut self.opponent_engine {
-                // Get the top 3 moves that result from a depth 1 search.
-                // Idea being that a human is likely to pick the best from the moves
-                // that seem immediately good, so we should model the opponent as doing the same.
-                let opponent_eval = opponent_engine.ab_search::<PV>(
-                    board,
-                    depth.min(2),
-                    AlphaBeta::new(),
-                    deadline,
-                )?;
-
-                if let Some(opponent_moves) = opponent_eval.moves() {
-                    let mut moves = BestMoves::new();
-                    for opponent_move in *opponent_moves {
-                        let next = board.with_move(opponent_move);
-
-                        let eval = if PV && moves.is_none() {
-                            -self
-                                .ab_search::<PV>(&next, depth - 1, -window, deadline)?
-                                .score()
-                        } else {
-                            -self
-                                .ab_search::<false>(&next, depth - 1, -window, deadline)?
-                                .score()
-                        };
-
-                        moves.push(RatedMove::new(eval, opponent_move));
-
-                        if let Pruned = window.negamax(eval) {
-                            break;
-                        }
-                    }
+            return Ok(entry);
+        }
+
+        // Opponent Modeling
+        // Deep in the tree, the opponent is only expected to play one of the moves
+        // that look good to it right away, so only those moves are answered.
+        if window.opponent() && self.opponent_model.applies(depth) {
+            let replies = self.opponent_moves(board, window, deadline)?;
+            // An expected score is only exact if every reply it averages is.
+            let weighted = self.opponent_model.kind == OpponentModelKind::Weighted;
+
+            let mut moves = BestMoves::new();
+            let mut outcomes = Vec::new();
+            for &(opponent_move, chance) in replies.iter() {
+                let next = board.with_move(opponent_move);
+                let reply_window = if weighted {
+                    window.full_width()
+                } else {
+                    window
+                };
+                if PV {
+                    self.pv.clear(window.ply + 1);
+                }
 
-                    let entry = window.new_table_entry(depth, moves);
-                    self.table.update::<PV>(board.last(), entry);
-                    return entry.mark();
+                let eval = if PV && moves.is_none() {
+                    -self
+                        .ab_search::<PV>(&next, depth - 1, -reply_window, deadline)?
+                        .score()
+                } else {
+                    -self
+                        .ab_search::<false>(&next, depth - 1, -reply_window, deadline)?
+                        .score()
+                };
+
+                // A weighted line follows the reply that is most likely to be played.
+                let improves = if weighted {
+                    moves.is_none()
                 } else {
-                    self.table.update::<PV>(board.last(), opponent_eval);
-                    return Ok(opponent_eval);
+                    window.alpha < eval
+                };
+                if PV && improves {
+                    self.pv.update(window.ply, opponent_move);
+                }
+
+                moves.push(RatedMove::new(eval, opponent_move));
+                outcomes.push((eval, chance));
+
+                if !weighted {
+                    if let Pruned = window.negamax(eval) {
+                        break;
+                    }
                 }
             }
+
+            // Without any legal moves, the normal search below scores the mate or stalemate.
+            // The modelled score is not what the position is worth against every opponent,
+            // so it is kept out of the shared table.
+            if let Some(&(likely, _)) = replies.first() {
+                if weighted {
+                    let expected = OpponentModel::expected_score(&outcomes);
+                    let moves = BestMoves::Best1(RatedMove::new(expected, likely));
+                    return Ok(original_window.new_table_entry(depth, moves));
+                }
+
+                return Ok(original_window.new_table_entry(depth, moves));
+            }
         }
 
         // Transposition Table Lookup
-        let pv = match self.table.sample::<PV>(&board.last(), &window, depth) {
+        let root = window.ply == 0;
+        // A cutoff would end the principal variation at this node, so in a PV node the
+        // entry only orders the moves, unless the position has no moves to search.
+        let pv = match self.table.sample::<PV>(board.last(), &window, depth) {
             TTableSample::Moves(moves) => moves,
-            TTableSample::Score(score) => return score.mark(),
+            TTableSample::Score(score)
+                if (!PV || matches!(score, TTableEntry::Edge(..)))
+                    && (!root || self.is_search_move(score.peek())) =>
+            {
+                return Ok(score)
+            }
+            TTableSample::Score(score) => score.moves().copied().unwrap_or_default(),
             TTableSample::None => BestMoves::new(),
         };
 
+        // Lazy SMP helpers try the root moves in a different order,
+        // so that they fill the shared table with different subtrees.
+        let pv = if root { pv.rotated(self.thread_id) } else { pv };
+
+        // The forward pruning below guesses from the evaluation, which
+        // means little in check or when the score is about a mate.
+        let check = *board.last().checkers() != EMPTY;
+        let static_eval = if !PV && !check && window.alpha.abs() < MATE_CUTOFF {
+            Some(evaluate(board.last()))
+        } else {
+            None
+        };
+
+        // Reverse Futility Pruning
+        if let Some(eval) = static_eval {
+            if window.beta < MATE_CUTOFF && self.pruning.reverse_futility(eval, window.beta, depth)
+            {
+                let entry = TTableEntry::NullCut(depth, eval);
+                return Ok(entry);
+            }
+        }
+
+        // Razoring
+        if let Some(eval) = static_eval {
+            if self.pruning.razoring(eval, window.alpha, depth) {
+                let eval = self.ab_qsearch(board.last(), window, 0);
+                if eval <= window.alpha {
+                    let entry = TTableEntry::Leaf(eval);
+                    return Ok(entry);
+                }
+            }
+        }
+
         // Null Move Pruning
+        // Two passes in a row would only search the same position shallower.
         let r: Depth = 2;
-        if !PV && depth > r && window.span() > 1 && window.beta < MATE_CUTOFF {
+        if !PV
+            && depth > r
+            && !board.after_null_move()
+            && self.null_move_ply <= window.ply
+            && window.beta < MATE_CUTOFF
+        {
             if let Some(null_board) = board.with_null_move() {
                 let null_eval = -self
                     .ab_search::<false>(&null_board, depth - r - 1, window.null_move(), deadline)?
                     .score();
 
                 if null_eval >= window.beta {
-                    let entry = TTableEntry::NullCut(depth, null_eval);
-                    return entry.mark();
+                    // In zugzwang passing is the best move there is, so a deep cutoff only
+                    // holds if a shallower search of the real moves fails high as well.
+                    let verified = if depth < NULL_VERIFICATION_DEPTH || self.null_move_ply > 0 {
+                        true
+                    } else {
+                        self.null_move_ply = window.ply + 3 * (depth - r) / 4;
+                        let verification =
+                            self.ab_search::<false>(board, depth - r - 1, window, deadline);
+                        self.null_move_ply = 0;
+                        verification?.score() >= window.beta
+                    };
+
+                    if verified {
+                        let entry = TTableEntry::NullCut(depth, null_eval);
+                        return Ok(entry);
+                    }
                 }
             }
         }
 
         // Normal Alpha Beta Search
-        let check = *board.last().checkers() != EMPTY;
+        let futile =
+            static_eval.is_some_and(|eval| self.pruning.futility(eval, window.alpha, depth));
+
+        let mut movegen = if root {
+            OrderedMoveGen::root_search(board.last(), pv, &self.search_moves, &self.excluded_moves)
+        } else {
+            OrderedMoveGen::full_search(board.last(), pv)
+        }
+        .with_refutations(
+            board.last(),
+            self.history.refutations(window.ply, board.last_move()),
+        );
+
+        // Singular Extension
+        let singular_move =
+            if !root && depth >= SINGULAR_DEPTH && window.extensions < EXTENSION_BUDGET {
+                self.singular_move(board, depth, window, deadline)?
+            } else {
+                None
+            };
 
         let mut moves = BestMoves::new();
-        for (move_count, movement) in OrderedMoveGen::full_search(board.last(), pv).enumerate() {
+        let mut quiets_tried = Vec::new();
+        let mut move_count = 0;
+        while let Some(movement) = movegen.next_with(&self.history) {
             let next = board.with_move(movement);
+            if PV {
+                self.pv.clear(window.ply + 1);
+            }
+            let quiet = is_quiet(board.last(), movement) && *next.last().checkers() == EMPTY;
+
+            // Futility and Late Move Pruning
+            // The first move is always searched, so that the node still has a score.
+            if static_eval.is_some()
+                && quiet
+                && 0 < move_count
+                && (futile || self.pruning.late_move(move_count, depth))
+            {
+                move_count += 1;
+                continue;
+            }
+
+            // Extensions
+            let extension = if window.extensions < EXTENSION_BUDGET
+                && (*next.last().checkers() != EMPTY
+                    || singular_move == Some(movement)
+                    || (PAWN_PUSH_EXTENSIONS && is_pawn_push_to_seventh(board.last(), movement)))
+            {
+                1
+            } else {
+                0
+            };
+            let new_depth = depth - 1 + extension;
+            let child_window = -window.extended(extension);
 
             let eval = if PV && move_count == 0 {
                 -self
-                    .ab_search::<PV>(&next, depth - 1, -window, deadline)?
+                    .ab_search::<PV>(&next, new_depth, child_window, deadline)?
                     .score()
             } else {
                 let reduction =
@@ -187,23 +592,54 @@ impl Engine {
                     }
                     .clamp(0, depth - 1);
 
-                let eval = -self
-                    .ab_search::<false>(&next, depth - reduction - 1, -window, deadline)?
+                // Principal Variation Search
+                // The moves after the first are expected to fail low, which a null window
+                // at alpha proves more cheaply. Those that do not are searched again.
+                let scout = -window.extended(extension).scout(window.alpha + 1);
+                let mut eval = -self
+                    .ab_search::<false>(&next, new_depth - reduction, scout, deadline)?
                     .score();
 
                 if 0 < reduction && window.alpha < eval {
-                    -self
-                        .ab_search::<false>(&next, depth - 1, -window, deadline)?
-                        .score()
-                } else {
-                    eval
+                    eval = -self
+                        .ab_search::<false>(&next, new_depth, scout, deadline)?
+                        .score();
+                }
+
+                if window.alpha < eval && eval < window.beta && scout.span() < window.span() {
+                    self.re_searches += 1;
+                    eval = -self
+                        .ab_search::<PV>(&next, new_depth, child_window, deadline)?
+                        .score();
                 }
+
+                eval
             };
 
+            // Only a move that raises alpha was searched with the full window to the end of its line.
+            if PV && window.alpha < eval {
+                self.pv.update(window.ply, movement);
+            }
+
             moves.push(RatedMove::new(eval, movement));
             if let Pruned = window.negamax(eval) {
+                if is_quiet(board.last(), movement) {
+                    self.history.update(
+                        board.last(),
+                        board.last_move(),
+                        window.ply,
+                        depth,
+                        movement,
+                        &quiets_tried,
+                    );
+                }
                 break;
             }
+
+            if is_quiet(board.last(), movement) {
+                quiets_tried.push(movement);
+            }
+            move_count += 1;
         }
 
         // Check for Checkmate or Draw
@@ -211,7 +647,7 @@ impl Engine {
             let check = *board.last().checkers() != EMPTY;
 
             let eval = if check {
-                -MATE
+                mated_in(window.ply)
             } else if window.opponent() {
                 -DRAW
             } else {
@@ -219,24 +655,115 @@ impl Engine {
             };
 
             let entry = TTableEntry::Edge(eval);
-            self.table.update::<PV>(board.last(), entry);
-            return entry.mark();
+            self.table.update::<PV>(board.last(), window.ply, entry);
+            return Ok(entry);
         }
 
         // Store and Return Results
+        // A root search with excluded moves is missing the best move, and a root search
+        // that failed its aspiration window is searched again, so neither of them
+        // may replace the last real result of the root in the table.
+        // An entry whose best move is not among the search moves never cuts off the root,
+        // so a shallower result replaces it, or the depth of the root would never grow.
         let entry = original_window.new_table_entry(depth, moves);
-        self.table.update::<PV>(board.last(), entry);
-        entry.mark()
+        if !root {
+            self.table.update::<PV>(board.last(), window.ply, entry);
+        } else if self.excluded_moves.is_empty() && matches!(entry, TTableEntry::ExactNode(..)) {
+            let stored = self.table.peek(board.last());
+            if self.is_search_move(stored.and_then(|stored| stored.peek())) {
+                self.table.update::<PV>(board.last(), window.ply, entry);
+            } else {
+                self.table.replace::<PV>(board.last(), window.ply, entry);
+            }
+        }
+        Ok(entry)
     }
 
-    pub fn min_search(&mut self, history: &BoardChain) -> TTableEntry {
-        return self
-            .ab_search::<false>(&history, 1, AlphaBeta::new(), &Deadline::none())
-            .expect("Expected Complete Search");
+    /// The move from the table, if every other move fails low against a bound a bit below
+    /// its score in a shallower search. Such a move is the only one that holds the score.
+    fn singular_move(
+        &mut self,
+        board: &BoardChain,
+        depth: Depth,
+        window: AlphaBeta,
+        deadline: &Deadline,
+    ) -> Result<Option<ChessMove>, ()> {
+        let entry = self.table.peek(board.last());
+        let (entry_depth, moves) = match entry.map(|entry| entry.root_relative(window.ply)) {
+            Some(TTableEntry::ExactNode(entry_depth, moves))
+            | Some(TTableEntry::LowerNode(entry_depth, moves)) => (entry_depth, moves),
+            _ => return Ok(None),
+        };
+
+        let table_move = match moves.peek() {
+            Some(table_move) if board.last().legal(table_move) => table_move,
+            _ => return Ok(None),
+        };
+
+        if entry_depth < depth - 3 || moves.score().abs() >= MATE_CUTOFF {
+            return Ok(None);
+        }
+
+        let singular_beta = moves.score() - SINGULAR_MARGIN * depth;
+        let scout = window.scout(singular_beta);
+
+        for movement in OrderedMoveGen::full_search(board.last(), BestMoves::new()) {
+            if movement == table_move {
+                continue;
+            }
+
+            let next = board.with_move(movement);
+            let eval = -self
+                .ab_search::<false>(&next, (depth - 1) / 2, -scout, deadline)?
+                .score();
+
+            if eval >= singular_beta {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(table_move))
+    }
+
+    /// The moves that the opponent model expects to be played, from a shallow search of each.
+    fn opponent_moves(
+        &mut self,
+        board: &BoardChain,
+        window: AlphaBeta,
+        deadline: &Deadline,
+    ) -> Result<Vec<(ChessMove, f64)>, ()> {
+        let mut rated = Vec::new();
+        for movement in OrderedMoveGen::full_search(board.last(), BestMoves::new()) {
+            let next = board.with_move(movement);
+            let eval = -self
+                .ab_search::<false>(
+                    &next,
+                    OPPONENT_SHALLOW_DEPTH - 1,
+                    -window.full_width(),
+                    deadline,
+                )?
+                .score();
+
+            rated.push(RatedMove::new(eval, movement));
+        }
+
+        rated.sort_by_key(|rated| Reverse(rated.score));
+        Ok(self.opponent_model.select(&rated))
     }
 
-    pub fn get_pv_line(&mut self, board: &Board) -> PVLine {
-        self.table.get_pv_line(board)
+    fn is_search_move(&self, movement: Option<ChessMove>) -> bool {
+        match movement {
+            Some(movement) => {
+                (self.search_moves.is_empty() || self.search_moves.contains(&movement))
+                    && !self.excluded_moves.contains(&movement)
+            }
+            None => true,
+        }
+    }
+
+    pub fn min_search(&mut self, history: &BoardChain) -> TTableEntry {
+        self.ab_search::<false>(history, 1, AlphaBeta::new(), &Deadline::none())
+            .expect("Expected Complete Search")
     }
 
     pub fn iterative_deepening_search(
@@ -244,7 +771,8 @@ impl Engine {
         history: &BoardChain,
         deadline: &Deadline,
     ) -> Result<TTableEntry, ()> {
-        let depth = match self.min_search(&history) {
+        let previous = self.min_search(history);
+        let depth = match previous {
             TTableEntry::Edge(..) => return Err(()),
             default => default.depth(),
         };
@@ -253,8 +781,60 @@ impl Engine {
             return Err(());
         }
 
-        let result = self.ab_search::<true>(&history, depth + 1, AlphaBeta::new(), deadline);
-        self.table.promote_pv_line(history.last());
+        // Aspiration Windows
+        let window = match self.research.take() {
+            Some((research_depth, window)) if research_depth == depth + 1 => window,
+            _ if depth < ASPIRATION_DEPTH => AlphaBeta::new(),
+            _ => AlphaBeta::aspiration(previous.score(), ASPIRATION_WINDOW),
+        };
+
+        // Lazy SMP: the helpers search the same root through the shared table,
+        // half of them one ply deeper, until the main thread completes its iteration.
+        let helper_deadline = deadline.for_helpers();
+        let mut helpers = std::mem::take(&mut self.helpers);
+
+        let result = thread::scope(|scope| {
+            for helper in helpers.iter_mut() {
+                let helper_deadline = &helper_deadline;
+                scope.spawn(move || {
+                    let mut helper_depth = depth + 1 + (helper.thread_id % 2) as Depth;
+                    while helper
+                        .ab_search::<true>(history, helper_depth, AlphaBeta::new(), helper_deadline)
+                        .is_ok()
+                    {
+                        helper_depth += 1;
+                    }
+                });
+            }
+
+            let result = self.ab_search::<true>(history, depth + 1, window, deadline);
+            let result = result.and_then(|result| {
+                // A failed search is returned as a bound, and the next call
+                // searches the same depth again with a wider window.
+                match result {
+                    TTableEntry::LowerNode(..) => {
+                        self.research = Some((depth + 1, window.widen_beta()))
+                    }
+                    TTableEntry::UpperNode(..) => {
+                        self.research = Some((depth + 1, window.widen_alpha()))
+                    }
+                    _ => {
+                        self.set_line(0, result);
+                        self.search_lines(history, depth + 1, deadline)?
+                    }
+                }
+
+                Ok(result)
+            });
+
+            helper_deadline.trigger();
+            result
+        });
+
+        self.helpers = helpers;
+        for pv in self.line_pvs.iter() {
+            self.table.promote_pv_line(history.last(), pv);
+        }
 
         if let Ok(score) = result {
             Ok(score)
@@ -263,26 +843,161 @@ impl Engine {
         }
     }
 
+    fn search_lines(
+        &mut self,
+        history: &BoardChain,
+        depth: Depth,
+        deadline: &Deadline,
+    ) -> Result<(), ()> {
+        if self.multi_pv <= 1 {
+            return Ok(());
+        }
+
+        let root_moves =
+            OrderedMoveGen::root_search(history.last(), BestMoves::new(), &self.search_moves, &[])
+                .count();
+
+        // Every line after the first searches the root again without the
+        // moves of the lines before it, so each line gets its own exact score.
+        for index in 1..self.multi_pv.min(root_moves) {
+            self.excluded_moves = self.lines[..index]
+                .iter()
+                .filter_map(TTableEntry::peek)
+                .collect();
+
+            let line = self.ab_search::<true>(history, depth, AlphaBeta::new(), deadline);
+            self.excluded_moves.clear();
+            self.set_line(index, line?);
+        }
+
+        Ok(())
+    }
+
+    /// Keeps a result of the root, along with the principal variation its search left behind.
+    fn set_line(&mut self, index: usize, line: TTableEntry) {
+        let pv = self.pv.line(0).to_vec();
+        if index < self.lines.len() {
+            self.lines[index] = line;
+            self.line_pvs[index] = pv;
+        } else {
+            self.lines.push(line);
+            self.line_pvs.push(pv);
+        }
+    }
+
+    /// The results of the root, one for each line of the MultiPV, best first.
+    pub fn get_lines(&mut self, history: &BoardChain) -> Vec<TTableEntry> {
+        if self.lines.is_empty() {
+            vec![self.min_search(history)]
+        } else {
+            self.lines.clone()
+        }
+    }
+
+    /// The principal variation of a line from `get_lines`, which is empty
+    /// until an iteration has completed.
+    pub fn get_pv(&self, index: usize) -> &[ChessMove] {
+        self.line_pvs.get(index).map_or(&[], Vec::as_slice)
+    }
+
+    /// The principal variation of the last search of the root, even one that failed its window.
+    pub fn get_root_pv(&self) -> &[ChessMove] {
+        self.pv.line(0)
+    }
+
     pub fn best_move(&mut self, history: &BoardChain) -> Option<ChessMove> {
-        self.min_search(&history).peek()
+        self.min_search(history).peek()
     }
 
     pub fn start_new_search(&mut self) -> Instant {
+        // The helpers share these tables, so they are aged only once.
+        self.table.new_search();
+
+        self.lines.clear();
+        self.line_pvs.clear();
+        self.research = None;
+        self.new_history();
+        self.reset_nodes();
+        Instant::now()
+    }
+
+    fn new_history(&mut self) {
+        self.history.new_search();
+
+        for helper in self.helpers.iter_mut() {
+            helper.new_history();
+        }
+    }
+
+    fn reset_nodes(&mut self) {
         self.nodes = 0;
+        self.tb_hits = 0;
+        self.shared_nodes.store(0, Ordering::Relaxed);
+        self.pv_nodes = 0;
+        self.re_searches = 0;
+        self.seldepth = 0;
+
+        for helper in self.helpers.iter_mut() {
+            helper.reset_nodes();
+        }
+    }
 
-        if let Some(opponent_engine) = &mut self.opponent_engine {
-            opponent_engine.start_new_search();
+    fn count_node(&mut self) {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(NODE_BATCH) {
+            self.shared_nodes.fetch_add(NODE_BATCH, Ordering::Relaxed);
         }
+    }
 
-        Instant::now()
+    /// The nodes of every thread, which the helpers report in batches while they search,
+    /// so that a node budget holds no matter how many threads share it.
+    fn shared_node_count(&self) -> usize {
+        self.shared_nodes.load(Ordering::Relaxed) + self.nodes % NODE_BATCH
     }
 
     pub fn get_node_count(&self) -> usize {
-        if let Some(opponent_engine) = &self.opponent_engine {
-            self.nodes + opponent_engine.get_node_count()
-        } else {
-            self.nodes
-        }
+        let helper_nodes: usize = self.helpers.iter().map(Engine::get_node_count).sum();
+        self.nodes + helper_nodes
+    }
+
+    /// The positions of every thread that were found in the tablebases.
+    pub fn get_tb_hits(&self) -> usize {
+        let helper_hits: usize = self.helpers.iter().map(Engine::get_tb_hits).sum();
+        self.tb_hits + helper_hits
+    }
+
+    /// The deepest ply that any search has reached, including the quiescence search.
+    pub fn get_seldepth(&self) -> Depth {
+        self.helpers
+            .iter()
+            .map(Engine::get_seldepth)
+            .fold(self.seldepth, Depth::max)
+    }
+
+    /// The nodes that were searched with an open window, as part of a principal variation.
+    /// Every other node was searched with a null window, or as a part of one.
+    pub fn get_pv_node_count(&self) -> usize {
+        let helper_nodes: usize = self.helpers.iter().map(Engine::get_pv_node_count).sum();
+        self.pv_nodes + helper_nodes
+    }
+
+    /// The moves that failed high against a null window, and were searched again with the full one.
+    pub fn get_re_search_count(&self) -> usize {
+        let helper_re_searches: usize = self.helpers.iter().map(Engine::get_re_search_count).sum();
+        self.re_searches + helper_re_searches
+    }
+
+    pub fn set_table_size(&mut self, table_size: usize) {
+        let threads = self.helpers.len() + 1;
+
+        // Release the old tables before allocating the new ones,
+        // so that both are never held in memory at the same time.
+        self.helpers.clear();
+        self.table = Arc::new(TTable::new(0));
+        self.table = Arc::new(TTable::new(table_size));
+
+        // Rebuild the helpers so that they share the new tables.
+        self.set_threads(threads);
     }
 
     pub fn memory_bytes(&self) -> usize {
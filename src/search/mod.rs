@@ -2,7 +2,13 @@ pub mod alpha_beta;
 pub mod board_chain;
 pub mod deadline;
 pub mod engine;
+pub mod move_history;
 pub mod movegen;
+pub mod opponent_model;
+pub mod pruning;
+pub mod pv_array;
+pub mod see;
+pub mod time_manager;
 
 pub type Depth = i64;
 
@@ -0,0 +1,121 @@
+use chess::{Board, ChessMove, Color, Piece, NUM_SQUARES};
+
+use crate::search::Depth;
+
+const KILLER_SLOTS: usize = 2;
+
+// The bound that history scores approach, but never pass.
+const MAX_HISTORY: i64 = 1 << 14;
+
+type Killers = [Option<ChessMove>; KILLER_SLOTS];
+
+/// A capture, a promotion or an en passant move is ordered by what it wins,
+/// so only the other moves are ordered by how they did elsewhere in the tree.
+pub fn is_quiet(board: &Board, movement: ChessMove) -> bool {
+    let source = movement.get_source();
+    let dest = movement.get_dest();
+
+    let en_passant =
+        board.piece_on(source) == Some(Piece::Pawn) && source.get_file() != dest.get_file();
+
+    board.piece_on(dest).is_none() && movement.get_promotion().is_none() && !en_passant
+}
+
+/// Quiet moves that caused beta cutoffs, remembered by the ply they were found at,
+/// by the side that played them, and by the move they answered.
+pub struct MoveHistory {
+    killers: Vec<Killers>,
+    butterfly: Box<[[[i64; NUM_SQUARES]; NUM_SQUARES]; 2]>,
+    counters: Box<[[Option<ChessMove>; NUM_SQUARES]; NUM_SQUARES]>,
+}
+
+impl MoveHistory {
+    pub fn new() -> MoveHistory {
+        MoveHistory {
+            killers: Vec::new(),
+            butterfly: Box::new([[[0; NUM_SQUARES]; NUM_SQUARES]; 2]),
+            counters: Box::new([[None; NUM_SQUARES]; NUM_SQUARES]),
+        }
+    }
+
+    pub fn new_search(&mut self) {
+        // Killers belong to the plies of the last position, while the
+        // history scores still say something about this one, just less.
+        self.killers.clear();
+
+        for score in self.butterfly.iter_mut().flatten().flatten() {
+            *score /= 2;
+        }
+    }
+
+    /// The killers of this ply followed by the counter to the previous move.
+    pub fn refutations(&self, ply: Depth, previous: Option<ChessMove>) -> [Option<ChessMove>; 3] {
+        let [first, second] = self
+            .killers
+            .get(ply as usize)
+            .copied()
+            .unwrap_or([None; KILLER_SLOTS]);
+
+        let counter = previous.and_then(|previous| {
+            self.counters[previous.get_source().to_index()][previous.get_dest().to_index()]
+        });
+
+        [first, second, counter]
+    }
+
+    pub fn score(&self, color: Color, movement: ChessMove) -> i64 {
+        self.butterfly[color.to_index()][movement.get_source().to_index()]
+            [movement.get_dest().to_index()]
+    }
+
+    /// Rewards the quiet move that caused a beta cutoff, and punishes
+    /// the quiet moves that were searched before it and did not.
+    pub fn update(
+        &mut self,
+        board: &Board,
+        previous: Option<ChessMove>,
+        ply: Depth,
+        depth: Depth,
+        cutoff: ChessMove,
+        tried: &[ChessMove],
+    ) {
+        let ply = ply as usize;
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; KILLER_SLOTS]);
+        }
+
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(cutoff) {
+            killers[1] = killers[0];
+            killers[0] = Some(cutoff);
+        }
+
+        if let Some(previous) = previous {
+            self.counters[previous.get_source().to_index()][previous.get_dest().to_index()] =
+                Some(cutoff);
+        }
+
+        let bonus = (depth * depth).min(MAX_HISTORY);
+        let color = board.side_to_move();
+
+        self.add_score(color, cutoff, bonus);
+        for &movement in tried {
+            self.add_score(color, movement, -bonus);
+        }
+    }
+
+    fn add_score(&mut self, color: Color, movement: ChessMove, bonus: i64) {
+        let score = &mut self.butterfly[color.to_index()][movement.get_source().to_index()]
+            [movement.get_dest().to_index()];
+
+        // Scores shrink towards zero as they grow, so that they stay bounded
+        // and a move that stops working loses its place quickly.
+        *score += bonus - *score * bonus.abs() / MAX_HISTORY;
+    }
+}
+
+impl Default for MoveHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
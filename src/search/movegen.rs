@@ -1,51 +1,241 @@
-use chess::{BitBoard, Board, ChessMove, MoveGen, EMPTY};
+use std::cmp::Reverse;
 
+use chess::{BitBoard, Board, ChessMove, MoveGen, Piece, EMPTY};
+
+use crate::search::move_history::{is_quiet, MoveHistory};
+use crate::search::see::see;
 use crate::transposition::best_moves::BestMoves;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    Captures,
+    Refutations,
+    Quiets,
+    Checks,
+    BadCaptures,
+    Done,
+}
+
+/// Hands out the moves of a board in stages: the moves from the table first,
+/// then captures of the most valuable pieces by their least valuable attackers,
+/// then the quiet moves that refuted other lines, then the other quiet moves by
+/// their history score, and finally the captures that lose material.
 pub struct OrderedMoveGen {
+    stage: Stage,
+    board: Board,
     pv_iter: BestMoves,
-    masks: std::array::IntoIter<BitBoard, 6>,
+    masks: std::array::IntoIter<BitBoard, 5>,
+    captures: Vec<ChessMove>,
+    bad_captures: Vec<ChessMove>,
+    quiet_moves: bool,
+    quiet_checks: bool,
+    refutations: Vec<ChessMove>,
+    quiets: Vec<ChessMove>,
     move_gen: MoveGen,
+    search_moves: Vec<ChessMove>,
+    excluded_moves: Vec<ChessMove>,
 }
 
 impl OrderedMoveGen {
-    fn initialize(mut self) -> Self {
-        self.move_gen.set_iterator_mask(self.masks.next().unwrap());
-        self
-    }
-
-    pub fn full_search(board: &Board, pv: BestMoves) -> OrderedMoveGen {
+    fn new(board: &Board, pv: BestMoves, quiet_moves: bool) -> OrderedMoveGen {
         OrderedMoveGen {
-            pv_iter: pv,
+            stage: Stage::Captures,
+            board: *board,
+            pv_iter: Self::legal_pv(board, pv),
             masks: [
-                *board.pieces(chess::Piece::Queen),
-                *board.pieces(chess::Piece::Rook),
-                *board.pieces(chess::Piece::Bishop),
-                *board.pieces(chess::Piece::Knight),
-                *board.pieces(chess::Piece::Pawn),
-                !EMPTY,
+                *board.pieces(Piece::Queen),
+                *board.pieces(Piece::Rook),
+                *board.pieces(Piece::Bishop),
+                *board.pieces(Piece::Knight),
+                *board.pieces(Piece::Pawn),
             ]
             .into_iter(),
+            captures: Vec::new(),
+            bad_captures: Vec::new(),
+            quiet_moves,
+            quiet_checks: false,
+            refutations: Vec::new(),
+            quiets: Vec::new(),
             move_gen: MoveGen::new_legal(board),
+            search_moves: Vec::new(),
+            excluded_moves: Vec::new(),
         }
-        .initialize()
+    }
+
+    fn legal_pv(board: &Board, pv: BestMoves) -> BestMoves {
+        // The table only verifies part of the key, so on a rare
+        // collision it can hand out moves from another position.
+        pv.filtered(|mv| board.legal(mv))
+    }
+
+    pub fn full_search(board: &Board, pv: BestMoves) -> OrderedMoveGen {
+        Self::new(board, pv, true)
+    }
+
+    pub fn root_search(
+        board: &Board,
+        pv: BestMoves,
+        search_moves: &[ChessMove],
+        excluded_moves: &[ChessMove],
+    ) -> OrderedMoveGen {
+        let mut movegen = Self::full_search(board, pv);
+        movegen.search_moves = search_moves.to_vec();
+        movegen.excluded_moves = excluded_moves.to_vec();
+        movegen.pv_iter = movegen.pv_iter.filtered(|mv| movegen.is_searchable(mv));
+        movegen
     }
 
     pub fn quiescence_search(board: &Board, pv: BestMoves) -> OrderedMoveGen {
-        OrderedMoveGen {
-            pv_iter: pv,
-            masks: [
-                *board.pieces(chess::Piece::Queen),
-                *board.pieces(chess::Piece::Rook),
-                *board.pieces(chess::Piece::Bishop),
-                *board.pieces(chess::Piece::Knight),
-                *board.pieces(chess::Piece::Pawn),
-                EMPTY,
-            ]
-            .into_iter(),
-            move_gen: MoveGen::new_legal(board),
+        Self::new(board, pv, false)
+    }
+
+    /// The captures of a quiescence search, followed by the
+    /// quiet moves that give check without losing material.
+    pub fn quiescence_checks(board: &Board, pv: BestMoves) -> OrderedMoveGen {
+        let mut movegen = Self::quiescence_search(board, pv);
+        movegen.quiet_checks = true;
+        movegen
+    }
+
+    /// Tries these quiet moves right after the captures, in the order given.
+    /// Moves that are not legal and quiet on this board are left out.
+    pub fn with_refutations(
+        mut self,
+        board: &Board,
+        refutations: impl IntoIterator<Item = Option<ChessMove>>,
+    ) -> OrderedMoveGen {
+        for mv in refutations.into_iter().flatten() {
+            if board.legal(mv)
+                && is_quiet(board, mv)
+                && !self.pv_iter.contains(mv)
+                && !self.refutations.contains(&mv)
+            {
+                self.refutations.push(mv);
+            }
+        }
+
+        self
+    }
+}
+
+impl OrderedMoveGen {
+    fn is_searchable(&self, mv: ChessMove) -> bool {
+        (self.search_moves.is_empty() || self.search_moves.contains(&mv))
+            && !self.excluded_moves.contains(&mv)
+    }
+
+    fn start_quiets(&mut self, history: Option<&MoveHistory>) {
+        self.stage = Stage::Quiets;
+        self.move_gen.set_iterator_mask(!EMPTY);
+
+        // Without a history the quiet moves are left in the order they are generated.
+        if let Some(history) = history {
+            self.quiets = self.move_gen.by_ref().collect();
+
+            // Promotions are not quiet, but they have no victim to be ordered by.
+            let color = self.board.side_to_move();
+            self.quiets.sort_by_key(|&mv| match mv.get_promotion() {
+                Some(Piece::Queen) => i64::MAX,
+                Some(..) => i64::MIN,
+                None => history.score(color, mv),
+            });
+        }
+    }
+
+    fn start_captures(&mut self, mask: BitBoard) {
+        self.move_gen.set_iterator_mask(mask);
+        self.captures = self.move_gen.by_ref().collect();
+
+        // The captures of one victim are popped by their least valuable attacker first.
+        let board = self.board;
+        self.captures
+            .sort_by_key(|mv| Reverse(board.piece_on(mv.get_source())));
+    }
+
+    fn is_losing_capture(&self, mv: ChessMove) -> bool {
+        // Taking a piece worth at least the attacker never loses material.
+        self.board.piece_on(mv.get_source()) > self.board.piece_on(mv.get_dest())
+            && see(&self.board, mv) < 0
+    }
+
+    fn next_ordered(&mut self, history: Option<&MoveHistory>) -> Option<ChessMove> {
+        if let Some(pv) = self.pv_iter.pop() {
+            self.move_gen.remove_move(pv.mv);
+            return Some(pv.mv);
+        }
+
+        match self.stage {
+            Stage::Captures => {
+                if let Some(mv) = self.captures.pop() {
+                    if !self.is_losing_capture(mv) {
+                        Some(mv)
+                    } else {
+                        // Losing captures are searched after the quiet moves,
+                        // and not at all when only captures are searched.
+                        if self.quiet_moves {
+                            self.bad_captures.push(mv);
+                        }
+                        self.next_ordered(history)
+                    }
+                } else if let Some(mask) = self.masks.next() {
+                    self.start_captures(mask);
+                    self.next_ordered(history)
+                } else {
+                    self.stage = if self.quiet_moves {
+                        Stage::Refutations
+                    } else if self.quiet_checks {
+                        self.move_gen.set_iterator_mask(!EMPTY);
+                        Stage::Checks
+                    } else {
+                        Stage::Done
+                    };
+                    self.next_ordered(history)
+                }
+            }
+            Stage::Refutations => {
+                if self.refutations.is_empty() {
+                    self.start_quiets(history);
+                    self.next_ordered(history)
+                } else {
+                    let mv = self.refutations.remove(0);
+                    self.move_gen.remove_move(mv);
+                    Some(mv)
+                }
+            }
+            Stage::Quiets => {
+                if let Some(mv) = self.quiets.pop().or_else(|| self.move_gen.next()) {
+                    Some(mv)
+                } else {
+                    self.stage = Stage::BadCaptures;
+                    self.next_ordered(history)
+                }
+            }
+            Stage::Checks => {
+                let board = self.board;
+                self.move_gen.find(|&mv| {
+                    *board.make_move_new(mv).checkers() != EMPTY && see(&board, mv) >= 0
+                })
+            }
+            Stage::BadCaptures => {
+                if self.bad_captures.is_empty() {
+                    self.stage = Stage::Done;
+                    None
+                } else {
+                    Some(self.bad_captures.remove(0))
+                }
+            }
+            Stage::Done => None,
+        }
+    }
+
+    /// The next move, with the quiet moves ordered by their history scores.
+    pub fn next_with(&mut self, history: &MoveHistory) -> Option<ChessMove> {
+        loop {
+            let mv = self.next_ordered(Some(history))?;
+            if self.is_searchable(mv) {
+                return Some(mv);
+            }
         }
-        .initialize()
     }
 }
 
@@ -53,16 +243,11 @@ impl Iterator for OrderedMoveGen {
     type Item = ChessMove;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(pv) = self.pv_iter.pop() {
-            self.move_gen.remove_move(pv.mv);
-            Some(pv.mv)
-        } else if let Some(mv) = self.move_gen.next() {
-            Some(mv)
-        } else if let Some(mask) = self.masks.next() {
-            self.move_gen.set_iterator_mask(mask);
-            self.next()
-        } else {
-            None
+        loop {
+            let mv = self.next_ordered(None)?;
+            if self.is_searchable(mv) {
+                return Some(mv);
+            }
         }
     }
 }
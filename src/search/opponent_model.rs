@@ -0,0 +1,125 @@
+use chess::ChessMove;
+
+use crate::evaluate::{Score, CENTIPAWN, MATE_CUTOFF};
+use crate::search::Depth;
+use crate::transposition::rated_move::RatedMove;
+
+// How deep the opponent looks at each of its moves before it picks one.
+pub const OPPONENT_SHALLOW_DEPTH: Depth = 2;
+
+// How much worse a move has to look for a weaker player to be e times less likely to play it.
+const WEIGHTED_TEMPERATURE: Score = 100 * CENTIPAWN;
+
+// The share of the likely moves that a weighted model searches.
+const WEIGHTED_MASS: f64 = 0.9;
+
+// In an expected score a mate counts as this much, which is more than any position
+// is worth, but keeps one unlikely mate from outweighing all of the other moves.
+const WEIGHTED_MATE: Score = 100 * 100 * CENTIPAWN;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpponentModelKind {
+    // The opponent is expected to find the best move, as in a plain search.
+    Off,
+    // The opponent plays one of the moves that look best in a shallow search.
+    TopK,
+    // The opponent plays the moves that look good in a shallow search more often,
+    // so a node is worth the score expected over the moves it is likely to play.
+    Weighted,
+}
+
+/// Models a weaker human opponent. Deep enough in the tree, the search only
+/// answers the moves that the opponent is likely to see, instead of every move.
+///
+/// This wins more against players who miss deep ideas, and loses
+/// more against the ones who do not, so it is off unless asked for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpponentModel {
+    pub kind: OpponentModelKind,
+    // The most opponent moves that are searched at a modelled node.
+    pub moves: usize,
+    // The shallowest remaining depth at which the opponent is modelled.
+    pub depth: Depth,
+}
+
+impl OpponentModel {
+    pub const DEFAULT: OpponentModel = OpponentModel {
+        kind: OpponentModelKind::Off,
+        moves: 3,
+        depth: 8,
+    };
+
+    pub fn applies(&self, depth: Depth) -> bool {
+        self.kind != OpponentModelKind::Off && depth >= self.depth
+    }
+
+    /// Picks the moves the opponent is likely to play, from moves sorted best first,
+    /// each with the chance that the opponent plays it.
+    pub fn select(&self, rated: &[RatedMove]) -> Vec<(ChessMove, f64)> {
+        let chances = Self::chances(rated);
+        let count = match self.kind {
+            OpponentModelKind::Off => rated.len(),
+            OpponentModelKind::TopK => self.moves,
+            OpponentModelKind::Weighted => Self::likely_moves(&chances).min(self.moves),
+        }
+        .max(1);
+
+        let total: f64 = chances.iter().take(count).sum();
+        rated
+            .iter()
+            .zip(chances)
+            .take(count)
+            .map(|(rated, chance)| (rated.mv, chance / total))
+            .collect()
+    }
+
+    /// The score of a weighted node, from the exact scores of its selected moves
+    /// and the chances that they are played.
+    pub fn expected_score(outcomes: &[(Score, f64)]) -> Score {
+        // When every move ends in a mate, the opponent is expected to find the best one.
+        if outcomes.iter().all(|(score, _)| score.abs() >= MATE_CUTOFF) {
+            return outcomes.iter().map(|&(score, _)| score).max().unwrap_or(0);
+        }
+
+        let expected: f64 = outcomes
+            .iter()
+            .map(|&(score, chance)| score.clamp(-WEIGHTED_MATE, WEIGHTED_MATE) as f64 * chance)
+            .sum();
+
+        (expected / CENTIPAWN as f64).round() as Score * CENTIPAWN
+    }
+
+    // How likely each move is to be played, falling off with how much worse it looks.
+    fn chances(rated: &[RatedMove]) -> Vec<f64> {
+        let best = match rated.first() {
+            Some(best) => best.score,
+            None => return Vec::new(),
+        };
+
+        let weights: Vec<f64> = rated
+            .iter()
+            .map(|rated| ((rated.score - best) as f64 / WEIGHTED_TEMPERATURE as f64).exp())
+            .collect();
+        let total: f64 = weights.iter().sum();
+
+        weights.iter().map(|weight| weight / total).collect()
+    }
+
+    fn likely_moves(chances: &[f64]) -> usize {
+        let mut mass = 0.0;
+        for (index, chance) in chances.iter().enumerate() {
+            mass += chance;
+            if mass >= WEIGHTED_MASS {
+                return index + 1;
+            }
+        }
+
+        chances.len()
+    }
+}
+
+impl Default for OpponentModel {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
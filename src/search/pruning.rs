@@ -0,0 +1,53 @@
+use crate::evaluate::{Score, CENTIPAWN};
+use crate::search::Depth;
+
+// The deepest nodes that each kind of forward pruning is tried at.
+pub const REVERSE_FUTILITY_DEPTH: Depth = 6;
+pub const FUTILITY_DEPTH: Depth = 3;
+pub const RAZORING_DEPTH: Depth = 2;
+pub const LATE_MOVE_DEPTH: Depth = 4;
+
+/// The margins of the forward pruning in the search. They are
+/// set through hidden UCI options, so that they can be tuned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pruning {
+    // Per ply of depth, how far the evaluation has to be above beta to cut the node.
+    pub reverse_futility_margin: Score,
+    // Per ply of depth, how far a quiet move has to be able to raise the evaluation.
+    pub futility_margin: Score,
+    // Per ply of depth, how far the evaluation has to be below alpha to drop into qsearch.
+    pub razoring_margin: Score,
+    // The quiet moves searched at depth one, growing with the square of the depth.
+    pub late_move_base: usize,
+}
+
+impl Pruning {
+    pub const DEFAULT: Pruning = Pruning {
+        reverse_futility_margin: 80 * CENTIPAWN,
+        futility_margin: 120 * CENTIPAWN,
+        razoring_margin: 300 * CENTIPAWN,
+        late_move_base: 3,
+    };
+
+    pub fn reverse_futility(&self, eval: Score, beta: Score, depth: Depth) -> bool {
+        depth <= REVERSE_FUTILITY_DEPTH && eval - self.reverse_futility_margin * depth >= beta
+    }
+
+    pub fn razoring(&self, eval: Score, alpha: Score, depth: Depth) -> bool {
+        depth <= RAZORING_DEPTH && eval + self.razoring_margin * depth <= alpha
+    }
+
+    pub fn futility(&self, eval: Score, alpha: Score, depth: Depth) -> bool {
+        depth <= FUTILITY_DEPTH && eval + self.futility_margin * depth <= alpha
+    }
+
+    pub fn late_move(&self, move_count: usize, depth: Depth) -> bool {
+        depth <= LATE_MOVE_DEPTH && move_count >= self.late_move_base + (depth * depth) as usize
+    }
+}
+
+impl Default for Pruning {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
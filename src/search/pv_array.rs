@@ -0,0 +1,46 @@
+use chess::ChessMove;
+
+use crate::search::Depth;
+
+/// The triangular array of principal variations found by the search, one line per ply.
+/// A node builds its line from its best move and the line its child left behind,
+/// so the line of the root is the exact sequence of moves that produced its score.
+pub struct PVArray {
+    lines: Vec<Vec<ChessMove>>,
+}
+
+impl PVArray {
+    pub fn new() -> PVArray {
+        PVArray { lines: Vec::new() }
+    }
+
+    /// Forgets the line of this ply, before a new node at it is searched.
+    pub fn clear(&mut self, ply: Depth) {
+        let ply = ply as usize;
+        if self.lines.len() < ply + 2 {
+            self.lines.resize_with(ply + 2, Vec::new);
+        }
+
+        self.lines[ply].clear();
+    }
+
+    /// Makes the move the best one at this ply, followed by the line of the ply after it.
+    pub fn update(&mut self, ply: Depth, movement: ChessMove) {
+        self.clear(ply);
+
+        let (line, child) = self.lines.split_at_mut(ply as usize + 1);
+        let line = &mut line[ply as usize];
+        line.push(movement);
+        line.extend_from_slice(&child[0]);
+    }
+
+    pub fn line(&self, ply: Depth) -> &[ChessMove] {
+        self.lines.get(ply as usize).map_or(&[], Vec::as_slice)
+    }
+}
+
+impl Default for PVArray {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,135 @@
+use chess::{
+    get_bishop_moves, get_king_moves, get_knight_moves, get_pawn_attacks, get_rook_moves, BitBoard,
+    Board, ChessMove, Color, Piece, Square, ALL_PIECES, EMPTY,
+};
+
+use crate::evaluate::pesto::gamephase::GamePhase;
+use crate::evaluate::pesto::phased_score::RawPhasedScore;
+use crate::evaluate::{Score, CENTIPAWN, MATE};
+
+// A capture can go on at most once for every piece on the board.
+const MAX_EXCHANGES: usize = 32;
+
+pub fn piece_value(piece: Piece, phase: &GamePhase) -> Score {
+    match piece {
+        // The king is never traded, so it can only take the last piece in an exchange.
+        Piece::King => MATE,
+        piece => RawPhasedScore::from_piece(piece).collapse(phase) * CENTIPAWN,
+    }
+}
+
+fn is_en_passant(board: &Board, movement: ChessMove) -> bool {
+    board.piece_on(movement.get_source()) == Some(Piece::Pawn)
+        && board.piece_on(movement.get_dest()).is_none()
+        && movement.get_source().get_file() != movement.get_dest().get_file()
+}
+
+/// The material a move wins before the other side can answer it,
+/// from the piece it captures and the piece it promotes to.
+pub fn capture_value(board: &Board, movement: ChessMove, phase: &GamePhase) -> Score {
+    let victim = match board.piece_on(movement.get_dest()) {
+        Some(victim) => piece_value(victim, phase),
+        None if is_en_passant(board, movement) => piece_value(Piece::Pawn, phase),
+        None => 0,
+    };
+
+    let promotion = match movement.get_promotion() {
+        Some(promotion) => piece_value(promotion, phase) - piece_value(Piece::Pawn, phase),
+        None => 0,
+    };
+
+    victim + promotion
+}
+
+fn attackers(board: &Board, square: Square, occupied: BitBoard) -> BitBoard {
+    let diagonal = board.pieces(Piece::Bishop) | board.pieces(Piece::Queen);
+    let straight = board.pieces(Piece::Rook) | board.pieces(Piece::Queen);
+    let pawns = board.pieces(Piece::Pawn);
+
+    let white_pawns = get_pawn_attacks(
+        square,
+        Color::Black,
+        *pawns & board.color_combined(Color::White),
+    );
+    let black_pawns = get_pawn_attacks(
+        square,
+        Color::White,
+        *pawns & board.color_combined(Color::Black),
+    );
+
+    (white_pawns
+        | black_pawns
+        | (get_knight_moves(square) & board.pieces(Piece::Knight))
+        | (get_king_moves(square) & board.pieces(Piece::King))
+        | (get_bishop_moves(square, occupied) & diagonal)
+        | (get_rook_moves(square, occupied) & straight))
+        & occupied
+}
+
+fn least_valuable(board: &Board, attackers: BitBoard) -> Option<(Square, Piece)> {
+    ALL_PIECES.iter().find_map(|&piece| {
+        let pieces = attackers & board.pieces(piece);
+        (pieces != EMPTY).then(|| (pieces.to_square(), piece))
+    })
+}
+
+/// Static Exchange Evaluation: the material that a move wins or loses,
+/// if both sides keep recapturing on its square with their least valuable piece
+/// for as long as that does not lose them material.
+pub fn see(board: &Board, movement: ChessMove) -> Score {
+    let phase = GamePhase::new(board);
+    let source = movement.get_source();
+    let dest = movement.get_dest();
+
+    let mut piece = match board.piece_on(source) {
+        Some(piece) => piece,
+        None => return 0,
+    };
+
+    let mut occupied = board.combined() ^ BitBoard::from_square(source);
+    let mut gains = [0; MAX_EXCHANGES];
+
+    gains[0] = capture_value(board, movement, &phase);
+
+    // The pawn taken en passant is not on the square that is moved to.
+    if is_en_passant(board, movement) {
+        let captured = Square::make_square(source.get_rank(), dest.get_file());
+        occupied ^= BitBoard::from_square(captured);
+    }
+
+    if let Some(promotion) = movement.get_promotion() {
+        piece = promotion;
+    }
+
+    let mut side = !board.side_to_move();
+    let mut exchanges = 0;
+
+    while exchanges + 1 < MAX_EXCHANGES {
+        let side_attackers = attackers(board, dest, occupied) & board.color_combined(side);
+        let (square, attacker) = match least_valuable(board, side_attackers) {
+            Some(attacker) => attacker,
+            None => break,
+        };
+
+        // Each entry is what the side to move has won so far, if it captures now.
+        exchanges += 1;
+        gains[exchanges] = piece_value(piece, &phase) - gains[exchanges - 1];
+
+        // Neither side can do better by capturing here, so the rest does not matter.
+        if (-gains[exchanges - 1]).max(gains[exchanges]) < 0 {
+            break;
+        }
+
+        occupied ^= BitBoard::from_square(square);
+        piece = attacker;
+        side = !side;
+    }
+
+    // Either side may stop capturing instead, whenever that is better for it.
+    while exchanges > 0 {
+        gains[exchanges - 1] = -(-gains[exchanges - 1]).max(gains[exchanges]);
+        exchanges -= 1;
+    }
+
+    gains[0]
+}
@@ -0,0 +1,68 @@
+use chess::ChessMove;
+
+use crate::evaluate::{Score, CENTIPAWN};
+use crate::search::deadline::Deadline;
+use crate::transposition::table_entry::TTableEntry;
+
+// A score drop this large between iterations is treated as the position falling apart.
+const SCORE_DROP: Score = 30 * CENTIPAWN;
+
+// After this many iterations with the same best move, the search is considered stable.
+const STABLE_ITERATIONS: u32 = 4;
+
+/// Decides between iterations whether the search should go one ply deeper,
+/// spending more time when the best move is unsettled and less when it is stable.
+pub struct TimeManager {
+    best_move: Option<ChessMove>,
+    score: Option<Score>,
+    changes: f64,
+    stable_iterations: u32,
+}
+
+impl TimeManager {
+    pub fn new() -> TimeManager {
+        TimeManager {
+            best_move: None,
+            score: None,
+            changes: 0.0,
+            stable_iterations: 0,
+        }
+    }
+
+    pub fn check_iteration(&mut self, deadline: &Deadline, result: &TTableEntry) -> bool {
+        let best_move = result.peek();
+        let score = result.score();
+
+        // Recent changes of the best move weigh more than older ones.
+        self.changes /= 2.0;
+        if self.best_move.is_some() && self.best_move != best_move {
+            self.changes += 1.0;
+            self.stable_iterations = 0;
+        } else {
+            self.stable_iterations += 1;
+        }
+
+        let mut scale = 1.0 + self.changes;
+
+        if let Some(previous) = self.score {
+            if previous - score >= SCORE_DROP {
+                scale *= 1.5;
+            }
+        }
+
+        if self.stable_iterations >= STABLE_ITERATIONS {
+            scale *= 0.5;
+        }
+
+        self.best_move = best_move;
+        self.score = Some(score);
+
+        deadline.check_time(scale)
+    }
+}
+
+impl Default for TimeManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
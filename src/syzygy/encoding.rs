@@ -0,0 +1,537 @@
+use std::sync::OnceLock;
+
+use chess::{Board, Color, Piece, ALL_PIECES};
+
+// Pieces are coded as in the table files: 1 to 6 from pawn to king, plus 8 for black.
+pub const BLACK: u8 = 8;
+
+// The most pieces of a table, and the most pieces of one kind that share a group.
+pub const MAX_PIECES: usize = 7;
+const MAX_GROUP: usize = 6;
+
+pub fn piece_code(piece: Piece, color: Color) -> u8 {
+    let kind = match piece {
+        Piece::Pawn => 1,
+        Piece::Knight => 2,
+        Piece::Bishop => 3,
+        Piece::Rook => 4,
+        Piece::Queen => 5,
+        Piece::King => 6,
+    };
+
+    match color {
+        Color::White => kind,
+        Color::Black => kind | BLACK,
+    }
+}
+
+fn is_pawn(code: u8) -> bool {
+    code & !BLACK == 1
+}
+
+fn file_of(square: usize) -> usize {
+    square & 7
+}
+
+fn rank_of(square: usize) -> usize {
+    square >> 3
+}
+
+// How far a square is above the a1-h8 diagonal, or below it when negative.
+fn off_diagonal(square: usize) -> i64 {
+    rank_of(square) as i64 - file_of(square) as i64
+}
+
+fn flip_file(square: usize) -> usize {
+    square ^ 7
+}
+
+fn flip_rank(square: usize) -> usize {
+    square ^ 56
+}
+
+fn flip_diagonal(square: usize) -> usize {
+    ((square >> 3) | (square << 3)) & 63
+}
+
+/// The numbering of squares and placements that every table shares.
+struct Maps {
+    // Ways to choose k of n squares.
+    binomial: [[u64; 64]; MAX_GROUP + 1],
+    // The squares a2-h7, numbered so the leading pawn is the one with the highest number.
+    pawns: [usize; 64],
+    lead_pawn_index: [[u64; 64]; MAX_GROUP],
+    lead_pawns_size: [[u64; 4]; MAX_GROUP],
+    // The 28 squares below the a1-h8 diagonal.
+    below_diagonal: [usize; 64],
+    // The 10 squares of the a1-d1-d4 triangle, with the diagonal last.
+    triangle: [usize; 64],
+    // The 462 legal placements of two kings, the first one in the triangle.
+    kings: [[u64; 64]; 10],
+}
+
+impl Maps {
+    fn new() -> Maps {
+        let mut maps = Maps {
+            binomial: [[0; 64]; MAX_GROUP + 1],
+            pawns: [0; 64],
+            lead_pawn_index: [[0; 64]; MAX_GROUP],
+            lead_pawns_size: [[0; 4]; MAX_GROUP],
+            below_diagonal: [0; 64],
+            triangle: [0; 64],
+            kings: [[0; 64]; 10],
+        };
+
+        maps.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..=MAX_GROUP.min(n) {
+                maps.binomial[k][n] = if k > 0 {
+                    maps.binomial[k - 1][n - 1]
+                } else {
+                    0
+                } + if k < n { maps.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        // The highest numbers go to the pawns nearest the edge, and the lowest ranks among those.
+        let mut available = 47;
+        for count in 1..MAX_GROUP {
+            for file in 0..4 {
+                let mut index = 0;
+                for rank in 1..7 {
+                    let square = rank * 8 + file;
+                    if count == 1 {
+                        maps.pawns[square] = available;
+                        maps.pawns[flip_file(square)] = available - 1;
+                        available = available.saturating_sub(2);
+                    }
+
+                    maps.lead_pawn_index[count][square] = index;
+                    index += maps.binomial[count - 1][maps.pawns[square]];
+                }
+
+                maps.lead_pawns_size[count][file] = index;
+            }
+        }
+
+        let mut code = 0;
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                maps.below_diagonal[square] = code;
+                code += 1;
+            }
+        }
+
+        let mut code = 0;
+        let mut diagonal = Vec::new();
+        for square in 0..28 {
+            if off_diagonal(square) < 0 && file_of(square) <= 3 {
+                maps.triangle[square] = code;
+                code += 1;
+            } else if off_diagonal(square) == 0 && file_of(square) <= 3 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            maps.triangle[square] = code;
+            code += 1;
+        }
+
+        // Two kings on the diagonal come last, after every other placement.
+        let mut code = 0;
+        let mut both_on_diagonal = Vec::new();
+        for index in 0..10 {
+            for first in 0..28 {
+                if maps.triangle[first] != index || (index == 0 && first != 1) {
+                    continue;
+                }
+
+                for second in 0..64 {
+                    let touching = rank_of(first).abs_diff(rank_of(second)) <= 1
+                        && file_of(first).abs_diff(file_of(second)) <= 1;
+
+                    if touching || (off_diagonal(first) == 0 && off_diagonal(second) > 0) {
+                        continue;
+                    } else if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        both_on_diagonal.push((index, second));
+                    } else {
+                        maps.kings[index][second] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (index, second) in both_on_diagonal {
+            maps.kings[index][second] = code;
+            code += 1;
+        }
+
+        maps
+    }
+
+    fn get() -> &'static Maps {
+        static MAPS: OnceLock<Maps> = OnceLock::new();
+        MAPS.get_or_init(Maps::new)
+    }
+}
+
+/// The material of a table, named like its files, e.g. `KRvKN`,
+/// with the pieces of white, the stronger side, before the `v`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Material {
+    pub name: String,
+    pub piece_count: usize,
+    pub has_pawns: bool,
+    // Whether some piece other than a king is the only one of its kind and color.
+    pub has_unique_pieces: bool,
+    // The pawns of the leading color, which has fewer of them, and of the other one.
+    pub pawn_count: [usize; 2],
+    // Whether both sides have the same pieces, so only white to move is stored.
+    pub symmetric: bool,
+}
+
+const PIECE_LETTERS: [(char, Piece); 6] = [
+    ('K', Piece::King),
+    ('Q', Piece::Queen),
+    ('R', Piece::Rook),
+    ('B', Piece::Bishop),
+    ('N', Piece::Knight),
+    ('P', Piece::Pawn),
+];
+
+impl Material {
+    pub fn from_name(name: &str) -> Option<Material> {
+        let (white, black) = name.split_once('v')?;
+        let count = |side: &str, piece: Piece| {
+            side.chars()
+                .filter(|&letter| PIECE_LETTERS.contains(&(letter, piece)))
+                .count()
+        };
+
+        let valid = |side: &str| {
+            side.chars()
+                .all(|letter| PIECE_LETTERS.iter().any(|&(known, _)| known == letter))
+                && count(side, Piece::King) == 1
+        };
+        if !valid(white) || !valid(black) || white.len() + black.len() > MAX_PIECES {
+            return None;
+        }
+
+        let white_pawns = count(white, Piece::Pawn);
+        let black_pawns = count(black, Piece::Pawn);
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+
+        Some(Material {
+            name: name.to_string(),
+            piece_count: white.len() + black.len(),
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: ALL_PIECES.iter().any(|&piece| {
+                piece != Piece::King && (count(white, piece) == 1 || count(black, piece) == 1)
+            }),
+            pawn_count: if white_leads {
+                [white_pawns, black_pawns]
+            } else {
+                [black_pawns, white_pawns]
+            },
+            symmetric: white == black,
+        })
+    }
+
+    /// The name of the material on a board, with the pieces of `first` before the `v`.
+    pub fn name_of(board: &Board, first: Color) -> String {
+        let side = |color: Color| -> String {
+            PIECE_LETTERS
+                .iter()
+                .flat_map(|&(letter, piece)| {
+                    let count = (board.pieces(piece) & board.color_combined(color)).popcnt();
+                    std::iter::repeat_n(letter, count as usize)
+                })
+                .collect()
+        };
+
+        format!("{}v{}", side(first), side(!first))
+    }
+
+    /// The pawns of the leading color come first in every table with pawns.
+    fn pawns_on_both_sides(&self) -> bool {
+        self.has_pawns && self.pawn_count[1] > 0
+    }
+
+    /// The tables of positions with pawns are split by the file of the leading pawn.
+    pub fn files(&self) -> usize {
+        if self.has_pawns {
+            4
+        } else {
+            1
+        }
+    }
+}
+
+/// How the pieces of a table are turned into an index, for one side to move and one pawn file.
+///
+/// The pieces are split into groups: the leading pawns or pieces, then every run of
+/// identical pieces. Each group is numbered on its own, and `order` decides which
+/// groups count for more in the combined index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Encoding {
+    pub pieces: Vec<u8>,
+    group_len: Vec<usize>,
+    // The factor of each group in the index, and the size of the whole table last.
+    group_index: Vec<u64>,
+}
+
+impl Encoding {
+    pub fn new(material: &Material, pieces: Vec<u8>, order: [u8; 2], file: usize) -> Encoding {
+        let maps = Maps::get();
+        let mut first_len: i64 = if material.has_pawns {
+            0
+        } else if material.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+
+        let mut group_len = vec![1];
+        for index in 1..pieces.len() {
+            first_len -= 1;
+            if first_len > 0 || pieces[index] == pieces[index - 1] {
+                *group_len.last_mut().expect("Groups are never empty") += 1;
+            } else {
+                group_len.push(1);
+            }
+        }
+
+        let groups = group_len.len();
+        let pawns_on_both_sides = material.pawns_on_both_sides();
+        let mut next = if pawns_on_both_sides { 2 } else { 1 };
+        let mut free_squares =
+            64 - group_len[0] - if pawns_on_both_sides { group_len[1] } else { 0 };
+
+        let mut group_index = vec![0; groups + 1];
+        let mut index = 1;
+        let mut k = 0;
+        while next < groups || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                group_index[0] = index;
+                index *= if material.has_pawns {
+                    maps.lead_pawns_size[group_len[0]][file]
+                } else if material.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] as usize {
+                group_index[1] = index;
+                index *= maps.binomial[group_len[1]][48 - group_len[0]];
+            } else {
+                group_index[next] = index;
+                index *= maps.binomial[group_len[next]][free_squares];
+                free_squares -= group_len[next];
+                next += 1;
+            }
+
+            k += 1;
+        }
+        group_index[groups] = index;
+
+        Encoding {
+            pieces,
+            group_len,
+            group_index,
+        }
+    }
+
+    /// The number of indices in the table, including the ones of illegal positions.
+    pub fn size(&self) -> u64 {
+        self.group_index[self.group_len.len()]
+    }
+
+    /// The index of a placement, whose leading pawns are already in front.
+    pub fn index(&self, material: &Material, placement: &Placement) -> u64 {
+        let maps = Maps::get();
+        let mut squares = placement.squares.clone();
+        let mut pieces = placement.pieces.clone();
+        let size = squares.len();
+        let lead_pawns = placement.lead_pawns;
+
+        // Put the pieces in the order of the table.
+        for i in lead_pawns..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|&j| self.pieces[i] == pieces[j]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        // Mirror the board so that the leading piece is on files a to d.
+        if file_of(squares[0]) > 3 {
+            for square in squares.iter_mut() {
+                *square = flip_file(*square);
+            }
+        }
+
+        let mut index = if material.has_pawns {
+            let mut index = maps.lead_pawn_index[lead_pawns][squares[0]];
+            squares[1..lead_pawns].sort_by_key(|&square| maps.pawns[square]);
+            for (i, &square) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+                index += maps.binomial[i][maps.pawns[square]];
+            }
+
+            index
+        } else {
+            // Mirror it again so the leading piece is on ranks 1 to 4,
+            // and once more so the first leading piece off the diagonal is below it.
+            if rank_of(squares[0]) > 3 {
+                for square in squares.iter_mut() {
+                    *square = flip_rank(*square);
+                }
+            }
+
+            for i in 0..self.group_len[0] {
+                let off = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+
+                if off > 0 {
+                    for square in squares[i..].iter_mut() {
+                        *square = flip_diagonal(*square);
+                    }
+                }
+                break;
+            }
+
+            if material.has_unique_pieces {
+                Self::unique_index(maps, &squares)
+            } else {
+                maps.kings[maps.triangle[squares[0]]][squares[1]]
+            }
+        };
+
+        index *= self.group_index[0];
+
+        // The other groups are numbered by their squares, skipping the ones already taken.
+        let mut start = self.group_len[0];
+        let mut remaining_pawns = material.pawns_on_both_sides();
+        for (group, &len) in self.group_len.iter().enumerate().skip(1) {
+            squares[start..start + len].sort_unstable();
+
+            let mut n = 0;
+            for i in 0..len {
+                let square = squares[start + i];
+                let taken = squares[..start].iter().filter(|&&s| s < square).count();
+                let skipped = if remaining_pawns { 8 } else { 0 };
+                n += maps.binomial[i + 1][square - taken - skipped];
+            }
+
+            remaining_pawns = false;
+            index += n * self.group_index[group];
+            start += len;
+        }
+
+        index
+    }
+
+    // The index of the first three pieces, with the first of them in the a1-d1-d4 triangle.
+    fn unique_index(maps: &Maps, squares: &[usize]) -> u64 {
+        let [first, second, third] = [squares[0], squares[1], squares[2]];
+        let adjust1 = (second > first) as usize;
+        let adjust2 = (third > first) as usize + (third > second) as usize;
+
+        let index = if off_diagonal(first) != 0 {
+            (maps.triangle[first] * 63 + (second - adjust1)) * 62 + third - adjust2
+        } else if off_diagonal(second) != 0 {
+            (6 * 63 + rank_of(first) * 28 + maps.below_diagonal[second]) * 62 + third - adjust2
+        } else if off_diagonal(third) != 0 {
+            6 * 63 * 62
+                + 4 * 28 * 62
+                + rank_of(first) * 7 * 28
+                + (rank_of(second) - adjust1) * 28
+                + maps.below_diagonal[third]
+        } else {
+            6 * 63 * 62
+                + 4 * 28 * 62
+                + 4 * 7 * 28
+                + rank_of(first) * 7 * 6
+                + (rank_of(second) - adjust1) * 6
+                + (rank_of(third) - adjust2)
+        };
+
+        index as u64
+    }
+}
+
+/// A board in the orientation of a table: white is the stronger side,
+/// and the leading pawns, if there are any, come first.
+#[derive(Clone, Debug)]
+pub struct Placement {
+    pub squares: Vec<usize>,
+    pub pieces: Vec<u8>,
+    pub lead_pawns: usize,
+    // The side to move, 0 for white in the colors of the table.
+    pub side: usize,
+    // The file of the leading pawn, counted from the nearest edge.
+    pub file: usize,
+}
+
+impl Placement {
+    /// Places a board whose material is `material`, or its mirror when `black_stronger`.
+    /// `lead_piece` is the first piece of the table, which is a pawn when it has any.
+    pub fn new(material: &Material, board: &Board, black_stronger: bool, lead_piece: u8) -> Self {
+        let maps = Maps::get();
+        let black_to_move = board.side_to_move() == Color::Black;
+
+        // A symmetric table only stores white to move, so black to move is mirrored.
+        let flip = black_stronger || (material.symmetric && black_to_move);
+        let (flip_color, flip_squares) = if flip { (BLACK, 56) } else { (0, 0) };
+
+        let mut squares = Vec::with_capacity(material.piece_count);
+        let mut pieces = Vec::with_capacity(material.piece_count);
+        let mut lead_pawns = 0;
+        let mut file = 0;
+
+        if material.has_pawns {
+            let color = if (lead_piece ^ flip_color) & BLACK == 0 {
+                Color::White
+            } else {
+                Color::Black
+            };
+
+            for square in board.pieces(Piece::Pawn) & board.color_combined(color) {
+                squares.push(square.to_index() ^ flip_squares);
+                pieces.push(lead_piece);
+            }
+            lead_pawns = squares.len();
+
+            // The leading pawn is the one nearest the edge, and the lowest one among those.
+            let lead = (0..lead_pawns)
+                .max_by_key(|&i| maps.pawns[squares[i]])
+                .expect("A table with pawns has a leading pawn");
+            squares.swap(0, lead);
+
+            file = file_of(squares[0]).min(7 - file_of(squares[0]));
+        }
+
+        for square in *board.combined() {
+            let (Some(piece), Some(color)) = (board.piece_on(square), board.color_on(square))
+            else {
+                continue;
+            };
+
+            let code = piece_code(piece, color) ^ flip_color;
+            if lead_pawns > 0 && is_pawn(code) && code == lead_piece {
+                continue;
+            }
+
+            squares.push(square.to_index() ^ flip_squares);
+            pieces.push(code);
+        }
+
+        Placement {
+            squares,
+            pieces,
+            lead_pawns,
+            side: (flip ^ black_to_move) as usize,
+            file,
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use crate::syzygy::encoding::Encoding;
+
+// The flags of a compressed table.
+pub const FLAG_STM: u8 = 1;
+pub const FLAG_MAPPED: u8 = 2;
+pub const FLAG_WIN_PLIES: u8 = 4;
+pub const FLAG_LOSS_PLIES: u8 = 8;
+pub const FLAG_WIDE: u8 = 16;
+pub const FLAG_SINGLE_VALUE: u8 = 128;
+
+// A symbol whose right half is this is a value, not a pair of symbols.
+const LEAF: usize = 0xfff;
+
+pub fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+pub fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// The values of one side and one pawn file of a table.
+///
+/// The values are compressed by recursive pairing: a symbol is either a value,
+/// or a pair of symbols that often follow each other. The symbols are stored with
+/// a canonical Huffman code, in blocks of the same size. A sparse index points to
+/// every `span` values, from where the block lengths lead to the value itself.
+/// Everything is kept as an offset into the file the table was read from.
+#[derive(Clone, Debug)]
+pub struct PairsData {
+    pub flags: u8,
+    pub encoding: Encoding,
+    block_size: usize,
+    span: u64,
+    sparse_index_size: usize,
+    blocks: usize,
+    block_length_size: usize,
+    // The shortest code, or the value itself when every value is the same.
+    min_len: u32,
+    // The first symbol of every code length, from the shortest.
+    lowest_symbols: usize,
+    // The lowest code of every length, left aligned, from the shortest.
+    base: Vec<u64>,
+    // How many values each symbol stands for, less one.
+    symbol_len: Vec<u8>,
+    tree: usize,
+    sparse_index: usize,
+    block_lengths: usize,
+    blocks_start: usize,
+    // Where the value maps of a DTZ table start, for each result.
+    pub maps: [usize; 4],
+}
+
+impl PairsData {
+    /// Reads the sizes of the table at `offset`, returning them and the offset past them.
+    /// The locations of the index, the block lengths and the blocks come later.
+    pub fn read(data: &[u8], offset: usize, encoding: Encoding) -> Option<(PairsData, usize)> {
+        let mut pairs = PairsData {
+            flags: *data.get(offset)?,
+            encoding,
+            block_size: 0,
+            span: 0,
+            sparse_index_size: 0,
+            blocks: 0,
+            block_length_size: 0,
+            min_len: 0,
+            lowest_symbols: 0,
+            base: Vec::new(),
+            symbol_len: Vec::new(),
+            tree: 0,
+            sparse_index: 0,
+            block_lengths: 0,
+            blocks_start: 0,
+            maps: [0; 4],
+        };
+
+        if pairs.flags & FLAG_SINGLE_VALUE != 0 {
+            pairs.min_len = *data.get(offset + 1)? as u32;
+            return Some((pairs, offset + 2));
+        }
+
+        let header = data.get(offset..offset + 10)?;
+        pairs.block_size = 1 << header[1];
+        pairs.span = 1 << header[2];
+        pairs.sparse_index_size = pairs.encoding.size().div_ceil(pairs.span) as usize;
+        let padding = header[3] as usize;
+        pairs.blocks = read_u32(header, 4) as usize;
+        pairs.block_length_size = pairs.blocks + padding;
+
+        let max_len = header[8] as u32;
+        pairs.min_len = header[9] as u32;
+        if max_len < pairs.min_len || max_len > 32 {
+            return None;
+        }
+
+        let lengths = (max_len - pairs.min_len + 1) as usize;
+        pairs.lowest_symbols = offset + 10;
+        data.get(pairs.lowest_symbols..pairs.lowest_symbols + 2 * lengths + 2)?;
+
+        // The longest codes have the lowest values. Each length starts where the
+        // codes of the next longer length end, halved to drop their extra bit.
+        let lowest = |len: usize| read_u16(data, pairs.lowest_symbols + 2 * len) as u64;
+        pairs.base = vec![0; lengths];
+        for len in (0..lengths - 1).rev() {
+            pairs.base[len] = (pairs.base[len + 1] + lowest(len)).checked_sub(lowest(len + 1))? / 2;
+        }
+        for (len, base) in pairs.base.iter_mut().enumerate() {
+            *base = base
+                .checked_shl(64 - len as u32 - pairs.min_len)
+                .unwrap_or(0);
+        }
+
+        let symbols_offset = pairs.lowest_symbols + 2 * lengths;
+        let symbols = read_u16(data, symbols_offset) as usize;
+        pairs.tree = symbols_offset + 2;
+        data.get(pairs.tree..pairs.tree + 3 * symbols)?;
+
+        pairs.symbol_len = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for symbol in 0..symbols {
+            if !visited[symbol] {
+                pairs.set_symbol_len(data, symbol, &mut visited)?;
+            }
+        }
+
+        let end = pairs.tree + 3 * symbols + (symbols & 1);
+        Some((pairs, end))
+    }
+
+    fn set_symbol_len(&mut self, data: &[u8], symbol: usize, visited: &mut [bool]) -> Option<()> {
+        visited[symbol] = true;
+
+        let right = self.right(data, symbol);
+        if right == LEAF {
+            self.symbol_len[symbol] = 0;
+            return Some(());
+        }
+
+        let left = self.left(data, symbol);
+        for child in [left, right] {
+            if !*visited.get(child)? {
+                self.set_symbol_len(data, child, visited)?;
+            }
+        }
+
+        self.symbol_len[symbol] = self.symbol_len[left]
+            .wrapping_add(self.symbol_len[right])
+            .wrapping_add(1);
+        Some(())
+    }
+
+    fn left(&self, data: &[u8], symbol: usize) -> usize {
+        let pair = self.tree + 3 * symbol;
+        ((data[pair + 1] as usize & 0xf) << 8) | data[pair] as usize
+    }
+
+    fn right(&self, data: &[u8], symbol: usize) -> usize {
+        let pair = self.tree + 3 * symbol;
+        ((data[pair + 2] as usize) << 4) | (data[pair + 1] as usize >> 4)
+    }
+
+    pub fn sparse_index_bytes(&self) -> usize {
+        6 * self.sparse_index_size
+    }
+
+    pub fn block_lengths_bytes(&self) -> usize {
+        2 * self.block_length_size
+    }
+
+    pub fn blocks_bytes(&self) -> usize {
+        self.blocks * self.block_size
+    }
+
+    pub fn locate(&mut self, sparse_index: usize, block_lengths: usize, blocks_start: usize) {
+        self.sparse_index = sparse_index;
+        self.block_lengths = block_lengths;
+        self.blocks_start = blocks_start;
+    }
+
+    fn block_length(&self, data: &[u8], block: usize) -> i64 {
+        read_u16(data, self.block_lengths + 2 * block) as i64
+    }
+
+    /// The value at `index`, which must be less than the size of the encoding.
+    pub fn value(&self, data: &[u8], index: u64) -> u16 {
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            return self.min_len as u16;
+        }
+
+        // The sparse index points to the middle of each span of values.
+        let entry = self.sparse_index + 6 * (index / self.span) as usize;
+        let mut block = read_u32(data, entry) as usize;
+        let mut offset = read_u16(data, entry + 4) as i64;
+        offset += (index % self.span) as i64 - (self.span / 2) as i64;
+
+        while offset < 0 {
+            block -= 1;
+            offset += self.block_length(data, block) + 1;
+        }
+        while offset > self.block_length(data, block) {
+            offset -= self.block_length(data, block) + 1;
+            block += 1;
+        }
+
+        // Walk the codes of the block until the one that holds the value.
+        let mut pointer = self.blocks_start + block * self.block_size;
+        let mut buffer =
+            ((read_u32_be(data, pointer) as u64) << 32) | read_u32_be(data, pointer + 4) as u64;
+        let mut buffer_size = 64;
+        pointer += 8;
+
+        let mut symbol = loop {
+            let mut len = 0;
+            while len + 1 < self.base.len() && buffer < self.base[len] {
+                len += 1;
+            }
+
+            let code = (buffer - self.base[len]) >> (64 - len as u32 - self.min_len);
+            let symbol = code as usize + read_u16(data, self.lowest_symbols + 2 * len) as usize;
+            if offset < self.symbol_len[symbol] as i64 + 1 {
+                break symbol;
+            }
+
+            offset -= self.symbol_len[symbol] as i64 + 1;
+            let bits = len as u32 + self.min_len;
+            buffer <<= bits;
+            buffer_size -= bits;
+
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (read_u32_be(data, pointer) as u64) << (64 - buffer_size);
+                pointer += 4;
+            }
+        };
+
+        // The pairs of a symbol follow each other, so the value is found by halving.
+        while self.symbol_len[symbol] != 0 {
+            let left = self.left(data, symbol);
+            if offset < self.symbol_len[left] as i64 + 1 {
+                symbol = left;
+            } else {
+                offset -= self.symbol_len[left] as i64 + 1;
+                symbol = self.right(data, symbol);
+            }
+        }
+
+        self.left(data, symbol) as u16
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use chess::{Board, BoardStatus, CastleRights, ChessMove, Color, MoveGen, Piece};
+
+use crate::search::board_chain::BoardChain;
+use crate::syzygy::encoding::Material;
+use crate::syzygy::table::{Table, TableKind};
+use crate::syzygy::Wdl;
+
+// Ranks the root moves, so that every certain win is above every win the fifty-move rule may spoil.
+const MAX_DTZ: i32 = 1 << 18;
+
+// Plies without a capture or a pawn move before the game is drawn.
+const FIFTY_MOVE_PLIES: i32 = 100;
+
+/// Whether a move captures, including en passant, which is the only time a pawn
+/// changes its file without landing on a piece.
+pub fn is_capture(board: &Board, movement: ChessMove) -> bool {
+    board.piece_on(movement.get_dest()).is_some()
+        || (board.piece_on(movement.get_source()) == Some(Piece::Pawn)
+            && movement.get_source().get_file() != movement.get_dest().get_file())
+}
+
+/// Whether a move starts the count of the fifty-move rule over.
+pub fn is_zeroing(board: &Board, movement: ChessMove) -> bool {
+    is_capture(board, movement) || board.piece_on(movement.get_source()) == Some(Piece::Pawn)
+}
+
+/// The distance to zeroing of a board whose best move captures or moves a pawn.
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => FIFTY_MOVE_PLIES + 1,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -FIFTY_MOVE_PLIES - 1,
+        Wdl::Loss => -1,
+    }
+}
+
+/// The files of one material, which are only read once they are probed.
+struct TableFiles {
+    material: Material,
+    paths: [Option<PathBuf>; 2],
+    tables: [OnceLock<Option<Table>>; 2],
+}
+
+impl TableFiles {
+    fn table(&self, kind: TableKind) -> Option<&Table> {
+        let index = kind as usize;
+        self.tables[index]
+            .get_or_init(|| {
+                let path = self.paths[index].as_ref()?;
+                Table::open(path, self.material.clone(), kind)
+            })
+            .as_ref()
+    }
+}
+
+/// The Syzygy tablebases found in a list of directories.
+///
+/// A WDL table tells whether a position is won, drawn or lost, and a DTZ table
+/// how many plies it takes to get to the next capture or pawn move while doing so.
+/// Neither knows about castling, and both leave the fifty-move rule to the caller.
+#[derive(Default)]
+pub struct Tablebases {
+    tables: HashMap<String, TableFiles>,
+    files: usize,
+    max_pieces: usize,
+}
+
+impl Tablebases {
+    /// Finds the tables in `paths`, which holds directories separated like the `PATH` variable.
+    pub fn open(paths: &str) -> Tablebases {
+        let mut tablebases = Tablebases::default();
+
+        for directory in env::split_paths(paths) {
+            let Ok(entries) = fs::read_dir(&directory) else {
+                continue;
+            };
+
+            for path in entries.flatten().map(|entry| entry.path()) {
+                let Some(kind) = [TableKind::Wdl, TableKind::Dtz]
+                    .into_iter()
+                    .find(|kind| path.extension().is_some_and(|ext| ext == kind.extension()))
+                else {
+                    continue;
+                };
+
+                let Some(material) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(Material::from_name)
+                else {
+                    continue;
+                };
+
+                let files = tablebases
+                    .tables
+                    .entry(material.name.clone())
+                    .or_insert_with(|| TableFiles {
+                        material: material.clone(),
+                        paths: [None, None],
+                        tables: [OnceLock::new(), OnceLock::new()],
+                    });
+
+                if files.paths[kind as usize].replace(path).is_none() {
+                    tablebases.files += 1;
+                }
+                if kind == TableKind::Wdl {
+                    tablebases.max_pieces = tablebases.max_pieces.max(material.piece_count);
+                }
+            }
+        }
+
+        tablebases
+    }
+
+    /// The number of table files that were found.
+    pub fn count(&self) -> usize {
+        self.files
+    }
+
+    /// The most pieces of a position that may be found in the tables.
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Whether a board is small enough for the tables, and has no castling rights.
+    fn covers(&self, board: &Board) -> bool {
+        board.combined().popcnt() as usize <= self.max_pieces
+            && [Color::White, Color::Black]
+                .iter()
+                .all(|&color| board.castle_rights(color) == CastleRights::NoRights)
+    }
+
+    fn table(&self, board: &Board, kind: TableKind) -> Option<&Table> {
+        [Color::White, Color::Black]
+            .iter()
+            .find_map(|&first| self.tables.get(&Material::name_of(board, first)))?
+            .table(kind)
+    }
+
+    fn table_wdl(&self, board: &Board) -> Option<Wdl> {
+        // Two bare kings are not worth a table.
+        if board.combined().popcnt() == 2 {
+            return Some(Wdl::Draw);
+        }
+
+        self.table(board, TableKind::Wdl)?.wdl(board)
+    }
+
+    /// The result of a board, and whether a capture, or a pawn move if `zeroing_moves`,
+    /// reaches it. The tables only hold the result of the best move, which may be a capture
+    /// the tables do not account for, like en passant, so captures are searched first.
+    fn search(&self, board: &Board, zeroing_moves: bool) -> Option<(Wdl, bool)> {
+        let legal_moves = MoveGen::new_legal(board).len();
+        let mut searched = 0;
+        let mut best = Wdl::Loss;
+
+        for movement in MoveGen::new_legal(board) {
+            let zeroing = if zeroing_moves {
+                is_zeroing(board, movement)
+            } else {
+                is_capture(board, movement)
+            };
+            if !zeroing {
+                continue;
+            }
+
+            searched += 1;
+            let (value, _) = self.search(&board.make_move_new(movement), false)?;
+            best = best.max(-value);
+            if best == Wdl::Win {
+                return Some((best, true));
+            }
+        }
+
+        let value = if searched > 0 && searched == legal_moves {
+            best
+        } else {
+            self.table_wdl(board)?
+        };
+
+        // Without another move, a loss zeroes right away, even if it is a checkmate.
+        if best >= value && (best > Wdl::Draw || (zeroing_moves && searched == legal_moves)) {
+            Some((best, true))
+        } else {
+            Some((value, false))
+        }
+    }
+
+    /// The result of a board, as far as it is in the tables.
+    pub fn probe_wdl(&self, board: &Board) -> Option<Wdl> {
+        if !self.covers(board) {
+            return None;
+        }
+
+        self.search(board, false).map(|(wdl, _)| wdl)
+    }
+
+    /// The plies to the next capture or pawn move of a board when both sides play their best,
+    /// positive when winning and negative when losing, or 0 for a draw.
+    /// A checkmated board is at -1, and a cursed win or blessed loss is more than 100 plies away.
+    pub fn probe_dtz(&self, board: &Board) -> Option<i32> {
+        if !self.covers(board) {
+            return None;
+        }
+
+        self.dtz(board)
+    }
+
+    fn dtz(&self, board: &Board) -> Option<i32> {
+        let (wdl, zeroing) = self.search(board, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        } else if zeroing {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        let table = self.table(board, TableKind::Dtz)?;
+        if let Some(dtz) = table.dtz(board, wdl) {
+            let cursed = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+            return Some((dtz + if cursed { FIFTY_MOVE_PLIES } else { 0 }) * wdl.signum());
+        }
+
+        // The table only stores the other side to move, so each move is looked up instead.
+        let mut best: Option<i32> = None;
+        for movement in MoveGen::new_legal(board) {
+            let zeroing = is_zeroing(board, movement);
+            let next = board.make_move_new(movement);
+
+            let mut dtz = if zeroing {
+                -dtz_before_zeroing(self.search(&next, false)?.0)
+            } else {
+                -self.dtz(&next)?
+            };
+
+            if dtz == 1 && next.status() == BoardStatus::Checkmate {
+                best = Some(1);
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+
+            if dtz.signum() == wdl.signum() && best.is_none_or(|best| dtz < best) {
+                best = Some(dtz);
+            }
+        }
+
+        Some(best.unwrap_or(-1))
+    }
+
+    /// The root moves that keep the best result, and among the wins the fastest to
+    /// zero the fifty-move count, so that a won endgame is converted and never shuffled.
+    /// Moves that lose are kept if they hold out the longest.
+    pub fn root_moves(&self, history: &BoardChain) -> Option<Vec<ChessMove>> {
+        let board = history.last();
+        if !self.covers(board) {
+            return None;
+        }
+
+        let halfmove_clock = history.halfmove_clock() as i32;
+        let mut ranked = Vec::new();
+        for movement in MoveGen::new_legal(board) {
+            let next = history.with_move(movement);
+
+            let mut dtz = if next.halfmove_clock() == 0 {
+                dtz_before_zeroing(-self.search(next.last(), false)?.0)
+            } else if next.is_draw() {
+                0
+            } else {
+                let dtz = -self.probe_dtz(next.last())?;
+                dtz + dtz.signum()
+            };
+
+            if dtz == 2 && next.last().status() == BoardStatus::Checkmate {
+                dtz = 1;
+            }
+
+            // A win that the fifty-move rule may still spoil is worth less than a certain one,
+            // and a loss that it may still save more than a certain one.
+            let rank = if dtz > 0 {
+                if dtz + halfmove_clock < FIFTY_MOVE_PLIES {
+                    MAX_DTZ - dtz
+                } else {
+                    MAX_DTZ / 2 - (dtz + halfmove_clock)
+                }
+            } else if dtz < 0 {
+                if -2 * dtz + halfmove_clock < FIFTY_MOVE_PLIES {
+                    -MAX_DTZ - dtz
+                } else {
+                    -MAX_DTZ / 2 + (-dtz + halfmove_clock)
+                }
+            } else {
+                0
+            };
+
+            ranked.push((rank, movement));
+        }
+
+        let best = ranked.iter().map(|&(rank, _)| rank).max()?;
+        Some(
+            ranked
+                .into_iter()
+                .filter(|&(rank, _)| rank == best)
+                .map(|(_, movement)| movement)
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,229 @@
+use std::fs;
+use std::path::Path;
+
+use chess::{Board, Color};
+
+use crate::syzygy::encoding::{Encoding, Material, Placement};
+use crate::syzygy::pairs::{
+    read_u16, PairsData, FLAG_LOSS_PLIES, FLAG_MAPPED, FLAG_STM, FLAG_WIDE, FLAG_WIN_PLIES,
+};
+use crate::syzygy::Wdl;
+
+// The flags of a table file, after its magic number.
+const FILE_SPLIT: u8 = 1;
+const FILE_HAS_PAWNS: u8 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+impl TableKind {
+    pub fn extension(self) -> &'static str {
+        match self {
+            TableKind::Wdl => "rtbw",
+            TableKind::Dtz => "rtbz",
+        }
+    }
+
+    pub fn magic(self) -> [u8; 4] {
+        match self {
+            TableKind::Wdl => [0x71, 0xe8, 0x23, 0x5d],
+            TableKind::Dtz => [0xd7, 0x66, 0x0c, 0xa5],
+        }
+    }
+}
+
+/// A Syzygy table file, either the results (WDL) or the distances to the next
+/// capture or pawn move (DTZ) of every position with one material.
+pub struct Table {
+    kind: TableKind,
+    material: Material,
+    data: Vec<u8>,
+    // The values of each file of the leading pawn, for each side to move.
+    pairs: Vec<Vec<PairsData>>,
+}
+
+impl Table {
+    pub fn open(path: &Path, material: Material, kind: TableKind) -> Option<Table> {
+        Self::read(fs::read(path).ok()?, material, kind)
+    }
+
+    /// Reads the layout of a table file, or `None` if it does not hold the given material.
+    pub fn read(data: Vec<u8>, material: Material, kind: TableKind) -> Option<Table> {
+        if data.get(..4)? != kind.magic() {
+            return None;
+        }
+
+        let flags = *data.get(4)?;
+        if (flags & FILE_HAS_PAWNS != 0) != material.has_pawns
+            || (flags & FILE_SPLIT != 0) == material.symmetric
+        {
+            return None;
+        }
+
+        // A DTZ table only stores one side to move, like a WDL table of symmetric material.
+        let sides = if kind == TableKind::Wdl && !material.symmetric {
+            2
+        } else {
+            1
+        };
+        let pawns_on_both_sides = material.has_pawns && material.pawn_count[1] > 0;
+
+        // Each file starts with the order of the groups, then the pieces, for both sides.
+        let mut offset = 5;
+        let mut encodings = Vec::new();
+        for file in 0..material.files() {
+            let first = *data.get(offset)?;
+            let second = if pawns_on_both_sides {
+                *data.get(offset + 1)?
+            } else {
+                0xff
+            };
+            let orders = [[first & 0xf, second & 0xf], [first >> 4, second >> 4]];
+            offset += 1 + pawns_on_both_sides as usize;
+
+            let pieces = data.get(offset..offset + material.piece_count)?;
+            offset += material.piece_count;
+
+            let side_encodings: Vec<Encoding> = (0..sides)
+                .map(|side| {
+                    let pieces = pieces
+                        .iter()
+                        .map(|&byte| if side == 0 { byte & 0xf } else { byte >> 4 })
+                        .collect();
+
+                    Encoding::new(&material, pieces, orders[side], file)
+                })
+                .collect();
+            encodings.push(side_encodings);
+        }
+        offset += offset & 1;
+
+        let mut pairs = Vec::new();
+        for side_encodings in encodings {
+            let mut side_pairs = Vec::new();
+            for encoding in side_encodings {
+                let (side, next) = PairsData::read(&data, offset, encoding)?;
+                side_pairs.push(side);
+                offset = next;
+            }
+            pairs.push(side_pairs);
+        }
+
+        // The values of a DTZ table may be mapped, with one map for each result.
+        if kind == TableKind::Dtz {
+            for side in pairs.iter_mut().flatten() {
+                if side.flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+
+                if side.flags & FLAG_WIDE != 0 {
+                    offset += offset & 1;
+                    for map in side.maps.iter_mut() {
+                        *map = offset + 2;
+                        offset += 2 * read_u16(data.get(..offset + 2)?, offset) as usize + 2;
+                    }
+                } else {
+                    for map in side.maps.iter_mut() {
+                        *map = offset + 1;
+                        offset += *data.get(offset)? as usize + 1;
+                    }
+                }
+            }
+            offset += offset & 1;
+        }
+
+        // Then come the sparse indices of every side, their block lengths and their blocks.
+        let mut sparse_indices = Vec::new();
+        for side in pairs.iter().flatten() {
+            sparse_indices.push(offset);
+            offset += side.sparse_index_bytes();
+        }
+
+        let mut block_lengths = Vec::new();
+        for side in pairs.iter().flatten() {
+            block_lengths.push(offset);
+            offset += side.block_lengths_bytes();
+        }
+
+        for ((side, sparse_index), block_lengths) in pairs
+            .iter_mut()
+            .flatten()
+            .zip(sparse_indices)
+            .zip(block_lengths)
+        {
+            offset = offset.next_multiple_of(64);
+            side.locate(sparse_index, block_lengths, offset);
+            offset += side.blocks_bytes();
+        }
+
+        if data.len() < offset {
+            return None;
+        }
+
+        Some(Table {
+            kind,
+            material,
+            data,
+            pairs,
+        })
+    }
+
+    /// The value stored for a board of this material, along with where it came from,
+    /// or `None` if it is a DTZ table that only stores the other side to move.
+    fn value(&self, board: &Board) -> Option<(u16, &PairsData)> {
+        let black_stronger = Material::name_of(board, Color::White) != self.material.name;
+        let lead_piece = self.pairs[0][0].encoding.pieces[0];
+        let placement = Placement::new(&self.material, board, black_stronger, lead_piece);
+
+        let file = &self.pairs[placement.file];
+        let pairs = &file[placement.side.min(file.len() - 1)];
+        if self.kind == TableKind::Dtz
+            && (pairs.flags & FLAG_STM) as usize != placement.side
+            && (!self.material.symmetric || self.material.has_pawns)
+        {
+            return None;
+        }
+
+        let index = pairs.encoding.index(&self.material, &placement);
+        Some((pairs.value(&self.data, index), pairs))
+    }
+
+    pub fn wdl(&self, board: &Board) -> Option<Wdl> {
+        let (value, _) = self.value(board)?;
+        Wdl::from_value(value as i32 - 2)
+    }
+
+    /// The plies to the next capture or pawn move of a board whose result is `wdl`,
+    /// which must not be a draw, or `None` if the table stores the other side to move.
+    pub fn dtz(&self, board: &Board, wdl: Wdl) -> Option<i32> {
+        let (mut value, pairs) = self.value(board)?;
+
+        if pairs.flags & FLAG_MAPPED != 0 {
+            let map = pairs.maps[match wdl {
+                Wdl::Win | Wdl::Draw => 0,
+                Wdl::Loss => 1,
+                Wdl::CursedWin => 2,
+                Wdl::BlessedLoss => 3,
+            }];
+
+            value = if pairs.flags & FLAG_WIDE != 0 {
+                read_u16(&self.data, map + 2 * value as usize)
+            } else {
+                self.data[map + value as usize] as u16
+            };
+        }
+
+        // Unless a flag says otherwise, the distances of a table are stored in moves.
+        let plies = match wdl {
+            Wdl::Win => pairs.flags & FLAG_WIN_PLIES != 0,
+            Wdl::Loss => pairs.flags & FLAG_LOSS_PLIES != 0,
+            _ => false,
+        };
+        let value = value as i32;
+
+        Some(if plies { value + 1 } else { 2 * value + 1 })
+    }
+}
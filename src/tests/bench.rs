@@ -0,0 +1,67 @@
+use std::str::FromStr;
+use std::time::Instant;
+
+use chess::Board;
+
+use crate::search::board_chain::BoardChain;
+use crate::search::deadline::Deadline;
+use crate::search::engine::Engine;
+use crate::search::Depth;
+
+const BENCH_DEPTH: Depth = 9;
+
+const BENCH_TABLE_SIZE: usize = 64 * 1024 * 1024;
+
+const BENCH_POSITIONS: &[&str] = &[
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+];
+
+/// Searches a fixed set of positions to a fixed depth, so that changes
+/// to the search can be compared by their node counts and speed.
+pub fn bench() {
+    let mut engine = Engine::new();
+    let mut total_nodes = 0;
+    let mut total_pv_nodes = 0;
+    let mut total_re_searches = 0;
+    let start = Instant::now();
+
+    for fen in BENCH_POSITIONS {
+        // Every position starts from an empty table, so that the
+        // node counts do not depend on the positions before it.
+        engine.set_table_size(BENCH_TABLE_SIZE);
+
+        let history = BoardChain::new(Board::from_str(fen).expect("Invalid bench FEN"));
+        let deadline = Deadline::depth(BENCH_DEPTH);
+
+        engine.start_new_search();
+        while engine
+            .iterative_deepening_search(&history, &deadline)
+            .is_ok()
+        {}
+
+        let nodes = engine.get_node_count();
+        total_nodes += nodes;
+        total_pv_nodes += engine.get_pv_node_count();
+        total_re_searches += engine.get_re_search_count();
+        println!("{:>12} nodes   {}", nodes, fen);
+    }
+
+    let time = start.elapsed();
+    println!("Nodes: {}", total_nodes);
+    println!(
+        "PV:    {} nodes, {} re-searches",
+        total_pv_nodes, total_re_searches
+    );
+    println!("Time:  {} ms", time.as_millis());
+    println!(
+        "NPS:   {}",
+        total_nodes as u128 * 1000 / time.as_millis().max(1)
+    );
+}
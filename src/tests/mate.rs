@@ -0,0 +1,107 @@
+use std::str::FromStr;
+
+use chess::Board;
+
+use crate::evaluate::{score_to_str, Score};
+use crate::search::board_chain::BoardChain;
+use crate::search::deadline::Deadline;
+use crate::search::engine::Engine;
+use crate::search::Depth;
+use crate::transposition::table_entry::TTableEntry;
+
+const MATE_DEPTH: Depth = 16;
+
+const MATE_TABLE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Searches a position with a known mate, and checks that
+/// the reported score is exactly the distance to it.
+fn assert_mate(fen: &str, expected: &str) {
+    let mut engine = Engine::with_table_size(MATE_TABLE_SIZE);
+
+    let history = BoardChain::new(Board::from_str(fen).expect("Invalid mate FEN"));
+    let deadline = Deadline::depth(MATE_DEPTH);
+
+    engine.start_new_search();
+    while engine
+        .iterative_deepening_search(&history, &deadline)
+        .is_ok()
+    {}
+
+    let score = score_to_str(engine.get_lines(&history)[0].score());
+    assert_eq!(score, expected, "{}", fen);
+}
+
+/// Runs a `go mate` search, which goes on until it proves a mate in at most `moves`.
+fn assert_mate_search(fen: &str, moves: Score, expected: &str) {
+    let mut engine = Engine::with_table_size(MATE_TABLE_SIZE);
+
+    let history = BoardChain::new(Board::from_str(fen).expect("Invalid mate FEN"));
+    let deadline = Deadline::mate(moves);
+
+    engine.start_new_search();
+    while let Ok(result) = engine.iterative_deepening_search(&history, &deadline) {
+        if let TTableEntry::ExactNode(..) = result {
+            if !deadline.check_score(result.score()) {
+                break;
+            }
+        }
+    }
+
+    let score = score_to_str(engine.get_lines(&history)[0].score());
+    assert_eq!(score, expected, "{}", fen);
+}
+
+// The distances to mate below were checked by brute force.
+
+#[test]
+fn back_rank_mate_in_one() {
+    assert_mate("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1", "mate 1");
+}
+
+#[test]
+fn rook_mate_in_one() {
+    assert_mate("6k1/8/6K1/8/8/8/8/R7 w - - 0 1", "mate 1");
+}
+
+#[test]
+fn zugzwang_mate_in_two() {
+    assert_mate("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1", "mate 2");
+}
+
+#[test]
+fn smothered_mate_in_two() {
+    assert_mate(
+        "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1",
+        "mate 2",
+    );
+}
+
+#[test]
+fn rook_and_bishop_mate_in_three() {
+    assert_mate("r5rk/5p1p/5R2/4B3/8/8/7P/7K w - - 0 1", "mate 3");
+}
+
+#[test]
+fn king_hunt_mate_in_three() {
+    assert_mate(
+        "r1b1kb1r/pppp1ppp/5q2/4n3/3KP3/2N3PN/PPP4P/R1BQ1B1R b kq - 0 1",
+        "mate 3",
+    );
+}
+
+#[test]
+fn mated_in_one() {
+    assert_mate("7k/8/6K1/8/8/8/8/R7 b - - 0 1", "mate -1");
+}
+
+#[test]
+fn mated_in_three() {
+    assert_mate("7k/8/5K2/8/8/8/8/6R1 b - - 0 1", "mate -3");
+}
+
+#[test]
+fn mate_search_goes_past_twice_the_mate_length() {
+    // Only the quiet Ra6 mates, and pruning hides it until depth 13 here,
+    // well past the 2 * moves + 8 plies a mate search used to stop at.
+    assert_mate_search("kbK5/pp6/1P6/8/8/8/R7/8 w - - 0 1", 2, "mate 2");
+}
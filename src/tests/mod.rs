@@ -1 +1,18 @@
+pub mod bench;
+#[cfg(test)]
+mod mate;
+#[cfg(test)]
+mod opponent_model;
+#[cfg(test)]
+mod packed_entry;
 pub mod play_self;
+#[cfg(test)]
+mod repetition;
+#[cfg(test)]
+mod see;
+#[cfg(test)]
+mod syzygy;
+#[cfg(test)]
+mod syzygy_generator;
+#[cfg(test)]
+mod time_limit;
@@ -0,0 +1,64 @@
+use std::str::FromStr;
+
+use chess::ChessMove;
+
+use crate::evaluate::{mate_in, mated_in, Score, CENTIPAWN};
+use crate::search::opponent_model::{OpponentModel, OpponentModelKind};
+use crate::transposition::rated_move::RatedMove;
+
+fn rated(moves: &[(Score, &str)]) -> Vec<RatedMove> {
+    moves
+        .iter()
+        .map(|&(score, movement)| {
+            RatedMove::new(
+                score,
+                ChessMove::from_str(movement).expect("Invalid test move"),
+            )
+        })
+        .collect()
+}
+
+fn model(kind: OpponentModelKind) -> OpponentModel {
+    OpponentModel {
+        kind,
+        ..OpponentModel::DEFAULT
+    }
+}
+
+#[test]
+fn weighted_replies_are_more_likely_when_they_look_better() {
+    let moves = rated(&[
+        (50 * CENTIPAWN, "e2e4"),
+        (20 * CENTIPAWN, "d2d4"),
+        (-400 * CENTIPAWN, "g1f3"),
+    ]);
+
+    let replies = model(OpponentModelKind::Weighted).select(&moves);
+    let total: f64 = replies.iter().map(|&(_, chance)| chance).sum();
+
+    assert!((total - 1.0).abs() < 1e-9);
+    assert!(replies.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    // The blunder is too unlikely to be worth searching.
+    assert_eq!(replies.len(), 2);
+}
+
+#[test]
+fn expected_score_weighs_every_reply() {
+    let outcomes = [(100 * CENTIPAWN, 0.75), (-100 * CENTIPAWN, 0.25)];
+    assert_eq!(OpponentModel::expected_score(&outcomes), 50 * CENTIPAWN);
+}
+
+#[test]
+fn an_unlikely_mate_does_not_outweigh_every_other_reply() {
+    let outcomes = [(mated_in(5), 0.01), (30 * CENTIPAWN, 0.99)];
+    let expected = OpponentModel::expected_score(&outcomes);
+
+    assert!(expected < 30 * CENTIPAWN);
+    assert!(expected > -100 * CENTIPAWN);
+}
+
+#[test]
+fn only_mates_keep_the_best_mate() {
+    let outcomes = [(mate_in(3), 0.5), (mate_in(7), 0.5)];
+    assert_eq!(OpponentModel::expected_score(&outcomes), mate_in(3));
+}
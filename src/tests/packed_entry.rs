@@ -0,0 +1,128 @@
+use std::str::FromStr;
+
+use chess::ChessMove;
+
+use crate::evaluate::{mate_in, mated_in, Score, CENTIPAWN, DRAW};
+use crate::transposition::best_moves::BestMoves;
+use crate::transposition::packed_entry::PackedEntry;
+use crate::transposition::rated_move::RatedMove;
+use crate::transposition::table_entry::TTableEntry;
+
+fn best_moves(moves: &[(Score, &str)]) -> BestMoves {
+    let mut best = BestMoves::new();
+    for &(score, movement) in moves {
+        let movement = ChessMove::from_str(movement).expect("Invalid test move");
+        best.push(RatedMove::new(score, movement));
+    }
+
+    best
+}
+
+fn round_trip(entry: TTableEntry) -> Option<TTableEntry> {
+    PackedEntry::from_words(PackedEntry::from(entry).to_words()).unpack()
+}
+
+#[test]
+fn exact_nodes_round_trip() {
+    let moves = best_moves(&[
+        (35 * CENTIPAWN, "e2e4"),
+        (20 * CENTIPAWN, "d2d4"),
+        (-300 * CENTIPAWN, "g1f3"),
+    ]);
+    let entry = TTableEntry::ExactNode(12, moves);
+    assert_eq!(round_trip(entry), Some(entry));
+}
+
+#[test]
+fn bound_nodes_round_trip() {
+    let lower = TTableEntry::LowerNode(7, best_moves(&[(150 * CENTIPAWN, "e7e8q")]));
+    assert_eq!(round_trip(lower), Some(lower));
+
+    let upper = TTableEntry::UpperNode(
+        3,
+        best_moves(&[(-80 * CENTIPAWN, "a2a4"), (-95 * CENTIPAWN, "h2h3")]),
+    );
+    assert_eq!(round_trip(upper), Some(upper));
+}
+
+#[test]
+fn mate_scores_round_trip() {
+    let mating = TTableEntry::ExactNode(
+        20,
+        best_moves(&[
+            (mate_in(9), "d1h5"),
+            (4 * CENTIPAWN, "g1f3"),
+            (mated_in(6), "f2f3"),
+        ]),
+    );
+    assert_eq!(round_trip(mating), Some(mating));
+
+    let mated = TTableEntry::UpperNode(
+        9,
+        best_moves(&[(mated_in(4), "e1e2"), (mated_in(2), "g2g4")]),
+    );
+    assert_eq!(round_trip(mated), Some(mated));
+
+    let behind = TTableEntry::LowerNode(
+        5,
+        best_moves(&[(2 * CENTIPAWN, "e2e4"), (mated_in(3), "f2f3")]),
+    );
+    assert_eq!(round_trip(behind), Some(behind));
+
+    let edge = TTableEntry::Edge(mated_in(0));
+    assert_eq!(round_trip(edge), Some(edge));
+}
+
+#[test]
+fn draws_and_scores_round_trip() {
+    for entry in [
+        TTableEntry::Edge(DRAW),
+        TTableEntry::Leaf(-45 * CENTIPAWN),
+        TTableEntry::NullCut(6, 310 * CENTIPAWN),
+        TTableEntry::ExactNode(4, best_moves(&[(DRAW, "b1c3")])),
+    ] {
+        assert_eq!(round_trip(entry), Some(entry));
+    }
+}
+
+// Every score the search produces is a multiple of this.
+const GRAIN: Score = CENTIPAWN / 24;
+
+#[test]
+fn gaps_round_trip_past_sixteen_bits() {
+    // Gaps of 16 bits used to clamp moves about 27 pawns behind the best.
+    for gap in [64510, 64511, 65535, 65536] {
+        let entry = TTableEntry::ExactNode(
+            8,
+            best_moves(&[
+                (10 * CENTIPAWN, "e2e4"),
+                (10 * CENTIPAWN - gap * GRAIN, "d2d4"),
+                (10 * CENTIPAWN - (gap + 1) * GRAIN, "g1f3"),
+            ]),
+        );
+        assert_eq!(round_trip(entry), Some(entry));
+    }
+}
+
+#[test]
+fn moves_far_behind_the_best_round_trip() {
+    let entry = TTableEntry::ExactNode(
+        8,
+        best_moves(&[
+            (3400 * 100 * CENTIPAWN, "e2e4"),
+            (-3400 * 100 * CENTIPAWN, "d2d4"),
+            (mated_in(7), "g1f3"),
+        ]),
+    );
+    assert_eq!(round_trip(entry), Some(entry));
+
+    let entry = TTableEntry::LowerNode(
+        8,
+        best_moves(&[
+            (mate_in(3), "e2e4"),
+            (3400 * 100 * CENTIPAWN, "d2d4"),
+            (-3400 * 100 * CENTIPAWN, "g1f3"),
+        ]),
+    );
+    assert_eq!(round_trip(entry), Some(entry));
+}
@@ -25,7 +25,7 @@ fn print_result(title: &str, result: TTableEntry) {
 
 pub fn play_self() {
     let mut engine = Engine::new();
-    engine.table.set_table_size(16000000000);
+    engine.set_table_size(16000000000);
 
     let mut board = BoardChain::new(Board::default());
 
@@ -0,0 +1,120 @@
+use std::str::FromStr;
+
+use chess::ChessMove;
+
+use crate::search::board_chain::BoardChain;
+use crate::uci::position::parse_position;
+
+fn to_move(movement: &str) -> ChessMove {
+    ChessMove::from_str(movement).expect("Invalid test move")
+}
+
+fn game(fen: &str, moves: &str) -> BoardChain<'static> {
+    parse_position(&format!("fen {} moves {}", fen, moves)).expect("Invalid test position")
+}
+
+// Plays the moves as the search would, with `0000` standing for a null move.
+fn search_is_draw(history: &BoardChain, moves: &[&str]) -> bool {
+    match moves.split_first() {
+        Some((&"0000", rest)) => match history.with_null_move() {
+            Some(next) => search_is_draw(&next, rest),
+            None => false,
+        },
+        Some((movement, rest)) => search_is_draw(&history.with_move(to_move(movement)), rest),
+        None => history.is_draw(),
+    }
+}
+
+const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[test]
+fn twofold_repetition_in_the_game_is_not_a_draw() {
+    assert!(!game(START, "g1f3 g8f6 f3g1 f6g8").is_draw());
+}
+
+#[test]
+fn threefold_repetition_in_the_game_is_a_draw() {
+    assert!(game(START, "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8").is_draw());
+}
+
+#[test]
+fn threefold_repetition_counts_the_earlier_occurrences() {
+    let history = game(START, "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8");
+    assert_eq!(history.repetitions(), 3);
+}
+
+#[test]
+fn repeating_the_root_inside_the_search_is_not_a_draw() {
+    assert!(!search_is_draw(
+        &game(START, "g1f3"),
+        &["g8f6", "f3g1", "f6g8", "g1f3"]
+    ));
+}
+
+#[test]
+fn twofold_repetition_inside_the_search_is_a_draw() {
+    assert!(search_is_draw(
+        &game(START, "g1f3"),
+        &["g8f6", "f3g1", "f6g8", "g1f3", "g8f6"]
+    ));
+}
+
+#[test]
+fn repeating_a_game_position_inside_the_search_needs_a_third_occurrence() {
+    assert!(search_is_draw(
+        &game(START, "g1f3 g8f6 f3g1 f6g8"),
+        &["g1f3", "g8f6", "f3g1", "f6g8"]
+    ));
+}
+
+#[test]
+fn lost_castling_rights_make_an_otherwise_equal_board_different() {
+    let history = game(START, "e2e4 e7e5 e1e2 e8e7 e2e1 e7e8");
+    assert_eq!(history.repetitions(), 1);
+}
+
+#[test]
+fn boards_after_castling_rights_are_lost_can_still_repeat() {
+    let history = game(START, "e2e4 e7e5 e1e2 e8e7 e2e1 e7e8 e1e2 e8e7");
+    assert_eq!(history.repetitions(), 2);
+}
+
+#[test]
+fn a_pawn_move_ends_the_repetition_history() {
+    let history = game(START, "g1f3 g8f6 f3g1 f6g8 e2e4");
+    assert_eq!(history.repetitions(), 1);
+}
+
+#[test]
+fn the_halfmove_clock_is_read_from_the_fen() {
+    assert!(!game("8/8/8/4k3/8/8/8/KQ6 w - - 99 80", "").is_draw());
+    assert!(game("8/8/8/4k3/8/8/8/KQ6 w - - 100 80", "").is_draw());
+}
+
+#[test]
+fn the_fifty_move_rule_draws_after_a_hundred_quiet_plies() {
+    assert!(game("8/8/8/4k3/8/8/8/KQ6 w - - 99 80", "b1c1").is_draw());
+}
+
+#[test]
+fn a_capture_resets_the_halfmove_clock() {
+    assert!(!game("8/8/8/4k3/8/8/1r6/KQ6 w - - 99 80", "b1b2").is_draw());
+}
+
+#[test]
+fn a_pawn_move_resets_the_halfmove_clock() {
+    assert!(!game("8/8/8/4k3/8/8/P7/KQ6 w - - 99 80", "a2a3").is_draw());
+}
+
+#[test]
+fn checkmate_on_the_hundredth_ply_is_not_a_draw() {
+    assert!(!game("7k/8/6K1/8/8/8/8/R7 w - - 99 80", "a1a8").is_draw());
+}
+
+#[test]
+fn a_repetition_in_the_search_does_not_reach_past_a_null_move() {
+    assert!(!search_is_draw(
+        &game(START, ""),
+        &["b1c3", "g8f6", "0000", "f6g8", "0000"]
+    ));
+}
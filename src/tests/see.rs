@@ -0,0 +1,52 @@
+use std::str::FromStr;
+
+use chess::{Board, ChessMove};
+
+use crate::evaluate::Score;
+use crate::search::see::see;
+
+fn see_of(fen: &str, movement: &str) -> Score {
+    let board = Board::from_str(fen).expect("Invalid test FEN");
+    let movement = ChessMove::from_str(movement).expect("Invalid test move");
+    see(&board, movement)
+}
+
+#[test]
+fn taking_an_undefended_pawn_wins_material() {
+    assert!(see_of("4k3/8/8/3p4/8/8/8/3RK3 w - - 0 1", "d1d5") > 0);
+}
+
+#[test]
+fn a_rook_taking_a_defended_pawn_loses_material() {
+    assert!(see_of("4k3/4p3/3p4/8/8/8/8/3RK3 w - - 0 1", "d1d6") < 0);
+}
+
+#[test]
+fn a_pawn_taking_a_defended_knight_wins_material() {
+    assert!(see_of("4k3/4p3/3n4/2P5/8/8/8/4K3 w - - 0 1", "c5d6") > 0);
+}
+
+#[test]
+fn a_rook_behind_the_attacker_joins_the_exchange() {
+    assert!(see_of("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5") > 0);
+}
+
+#[test]
+fn the_king_cannot_take_a_defended_piece() {
+    assert!(see_of("4k3/8/8/3p4/4K3/8/8/3r4 w - - 0 1", "e4d5") < 0);
+}
+
+#[test]
+fn capturing_en_passant_wins_the_pawn() {
+    assert!(see_of("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6") > 0);
+}
+
+#[test]
+fn a_quiet_move_to_a_safe_square_neither_wins_nor_loses() {
+    assert_eq!(see_of("4k3/8/8/8/8/8/8/3RK3 w - - 0 1", "d1d4"), 0);
+}
+
+#[test]
+fn a_quiet_move_to_an_attacked_square_loses_the_piece() {
+    assert!(see_of("4k3/8/8/4p3/8/8/8/3RK3 w - - 0 1", "d1d4") < 0);
+}
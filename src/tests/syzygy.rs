@@ -0,0 +1,181 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use chess::{Board, BoardStatus, ChessMove};
+
+use crate::search::board_chain::BoardChain;
+use crate::search::deadline::Deadline;
+use crate::search::engine::Engine;
+use crate::syzygy::probe::Tablebases;
+use crate::syzygy::Wdl;
+use crate::tests::syzygy_generator::FIXTURES;
+use crate::uci::options::UCIOptions;
+
+const TABLE_SIZE: usize = 16 * 1024 * 1024;
+
+fn board(fen: &str) -> Board {
+    Board::from_str(fen).expect("Invalid test FEN")
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::with_table_size(TABLE_SIZE);
+    engine.set_tablebases(Arc::new(Tablebases::open(FIXTURES)));
+    engine
+}
+
+/// Searches the root the way the UCI thread does, and returns the move it would play.
+fn best_move(engine: &mut Engine, history: &BoardChain, depth: i64) -> ChessMove {
+    engine.start_new_search();
+    engine.set_search_moves(&[]);
+    engine.probe_root(history);
+    while engine
+        .iterative_deepening_search(history, &Deadline::depth(depth))
+        .is_ok()
+    {}
+
+    engine.get_lines(history)[0]
+        .peek()
+        .expect("Position has a move")
+}
+
+#[test]
+fn finds_every_fixture() {
+    let tablebases = Tablebases::open(FIXTURES);
+    assert_eq!(tablebases.count(), 10);
+    assert_eq!(tablebases.max_pieces(), 3);
+}
+
+#[test]
+fn missing_directories_have_no_tables() {
+    let tablebases = Tablebases::open("/nonexistent/syzygy");
+    assert_eq!(tablebases.count(), 0);
+    assert_eq!(tablebases.probe_wdl(&Board::default()), None);
+}
+
+#[test]
+fn probes_the_result_for_either_side() {
+    let tablebases = Tablebases::open(FIXTURES);
+    let wdl = |fen| tablebases.probe_wdl(&board(fen));
+
+    assert_eq!(wdl("4k3/8/8/8/8/8/8/3QK3 w - - 0 1"), Some(Wdl::Win));
+    assert_eq!(wdl("4k3/8/8/8/8/8/8/3QK3 b - - 0 1"), Some(Wdl::Loss));
+    // With colors flipped, the table of the stronger side is read mirrored.
+    assert_eq!(wdl("3qk3/8/8/8/8/8/8/4K3 b - - 0 1"), Some(Wdl::Win));
+    assert_eq!(wdl("3qk3/8/8/8/8/8/8/4K3 w - - 0 1"), Some(Wdl::Loss));
+}
+
+#[test]
+fn probes_draws() {
+    let tablebases = Tablebases::open(FIXTURES);
+    let wdl = |fen| tablebases.probe_wdl(&board(fen));
+
+    // A queen next to the king is taken.
+    assert_eq!(wdl("8/8/8/8/8/8/1q6/K6k w - - 0 1"), Some(Wdl::Draw));
+    // A lone minor piece cannot mate.
+    assert_eq!(wdl("4k3/8/8/8/8/8/8/3BK3 w - - 0 1"), Some(Wdl::Draw));
+    assert_eq!(wdl("4k3/8/8/8/8/8/8/3NK3 b - - 0 1"), Some(Wdl::Draw));
+    // Stalemate.
+    assert_eq!(wdl("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"), Some(Wdl::Draw));
+}
+
+#[test]
+fn probes_pawn_endings() {
+    let tablebases = Tablebases::open(FIXTURES);
+    let wdl = |fen| tablebases.probe_wdl(&board(fen));
+
+    // A king on the sixth rank in front of its pawn wins, whoever is to move.
+    assert_eq!(wdl("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Some(Wdl::Win));
+    assert_eq!(wdl("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), Some(Wdl::Loss));
+    // A rook pawn does not get the defending king out of the corner.
+    assert_eq!(wdl("k7/8/K7/P7/8/8/8/8 w - - 0 1"), Some(Wdl::Draw));
+}
+
+#[test]
+fn does_not_probe_too_many_pieces_or_castling() {
+    let tablebases = Tablebases::open(FIXTURES);
+
+    assert_eq!(
+        tablebases.probe_wdl(&board("4k3/8/8/3r4/8/8/3Q4/4K3 w - - 0 1")),
+        None
+    );
+    assert_eq!(
+        tablebases.probe_wdl(&board("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1")),
+        None
+    );
+}
+
+#[test]
+fn probes_the_distance_to_zeroing() {
+    let tablebases = Tablebases::open(FIXTURES);
+    let dtz = |fen| tablebases.probe_dtz(&board(fen));
+
+    // A mate zeroes the count, and the side that is mated has lost right away.
+    assert_eq!(dtz("6k1/8/6K1/8/8/8/8/R7 w - - 0 1"), Some(1));
+    assert_eq!(dtz("R5k1/8/6K1/8/8/8/8/8 b - - 0 1"), Some(-1));
+    // A pawn move, here a promotion, zeroes as well.
+    assert_eq!(dtz("8/4P3/8/8/8/8/k7/4K3 w - - 0 1"), Some(1));
+
+    // Black to move is not stored, so it is found through the moves of white.
+    let loss = dtz("8/8/8/4k3/8/8/8/R3K3 b - - 0 1").expect("KRvK is in the fixtures");
+    let win = dtz("8/8/8/4k3/8/8/8/R3K3 w - - 0 1").expect("KRvK is in the fixtures");
+    assert!(loss < -1, "{}", loss);
+    assert!(win > 1, "{}", win);
+}
+
+#[test]
+fn keeps_the_root_moves_that_zero_the_fastest() {
+    let tablebases = Tablebases::open(FIXTURES);
+    let history = BoardChain::new(board("6k1/8/6K1/8/8/8/8/R7 w - - 0 1"));
+
+    let moves = tablebases
+        .root_moves(&history)
+        .expect("KRvK is in the fixtures");
+    assert_eq!(
+        moves,
+        vec![ChessMove::from_str("a1a8").expect("Valid move")]
+    );
+}
+
+#[test]
+fn converts_a_won_rook_ending() {
+    let tablebases = Tablebases::open(FIXTURES);
+    let start = board("8/8/8/4k3/8/8/8/R3K3 w - - 0 1");
+    let dtz = tablebases
+        .probe_dtz(&start)
+        .expect("KRvK is in the fixtures");
+
+    // Both sides follow the tables, so the mate comes exactly when they say.
+    let mut engine = engine();
+    let mut history = BoardChain::new(start);
+    for _ in 0..dtz {
+        let movement = best_move(&mut engine, &history, 3);
+        history = history.take_move(movement);
+    }
+
+    assert_eq!(history.last().status(), BoardStatus::Checkmate);
+}
+
+#[test]
+fn counts_tablebase_hits_after_captures() {
+    let mut engine = engine();
+    let history = BoardChain::new(board("4k3/8/8/3r4/8/8/3Q4/4K3 w - - 0 1"));
+
+    best_move(&mut engine, &history, 4);
+    assert!(engine.get_tb_hits() > 0);
+}
+
+#[test]
+fn syzygy_path_is_an_empty_string_by_default() {
+    let mut options = UCIOptions::new();
+    assert_eq!(options.syzygy_path(), "");
+
+    options
+        .set("SyzygyPath", FIXTURES)
+        .expect("SyzygyPath is an option");
+    assert_eq!(options.syzygy_path(), FIXTURES);
+
+    options
+        .set("SyzygyPath", "<empty>")
+        .expect("SyzygyPath is an option");
+    assert_eq!(options.syzygy_path(), "");
+}
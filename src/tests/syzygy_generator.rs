@@ -0,0 +1,592 @@
+//! Writes the three piece tables in `fixtures/syzygy`, which the tablebase tests probe.
+//! Nothing here runs by default, so after changing the format, write them again with
+//! `cargo test --release generate_syzygy_fixtures -- --ignored`.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use chess::{Board, BoardBuilder, BoardStatus, Color, MoveGen, Piece, Square, ALL_SQUARES};
+
+use crate::syzygy::encoding::{piece_code, Encoding, Material, Placement};
+use crate::syzygy::pairs::{FLAG_LOSS_PLIES, FLAG_SINGLE_VALUE, FLAG_WIN_PLIES};
+use crate::syzygy::probe::{is_zeroing, Tablebases};
+use crate::syzygy::table::{Table, TableKind};
+use crate::syzygy::Wdl;
+
+pub const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/syzygy");
+
+// The pieces that join the two kings, with the pawn last, since it promotes into the others.
+const EXTRA_PIECES: [Piece; 5] = [
+    Piece::Queen,
+    Piece::Rook,
+    Piece::Bishop,
+    Piece::Knight,
+    Piece::Pawn,
+];
+
+const POSITIONS: usize = 64 * 64 * 64 * 2;
+
+// Each block holds 1 KiB of codes, and the sparse index points into every 256 values.
+const BLOCK_SIZE_LOG2: u8 = 10;
+const SPAN_LOG2: u8 = 8;
+
+/// Numbers a position of a white king, a black king and one more white piece.
+fn position_id(white_king: usize, black_king: usize, extra: usize, side: Color) -> usize {
+    ((white_king * 64 + black_king) * 64 + extra) * 2 + (side == Color::Black) as usize
+}
+
+fn id_of(board: &Board, extra: Piece) -> usize {
+    let square = |piece: Piece, color: Color| {
+        (board.pieces(piece) & board.color_combined(color))
+            .to_square()
+            .to_index()
+    };
+
+    position_id(
+        square(Piece::King, Color::White),
+        square(Piece::King, Color::Black),
+        square(extra, Color::White),
+        board.side_to_move(),
+    )
+}
+
+/// The board of a position, or `None` if it is not legal.
+fn board_of(id: usize, extra: Piece) -> Option<Board> {
+    let side = if id.is_multiple_of(2) {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let extra_square = (id / 2) % 64;
+    let black_king = (id / 128) % 64;
+    let white_king = id / (128 * 64);
+
+    let touching = (white_king / 8).abs_diff(black_king / 8) <= 1
+        && (white_king % 8).abs_diff(black_king % 8) <= 1;
+    let back_rank = extra == Piece::Pawn && !(8..56).contains(&extra_square);
+    if touching || back_rank || extra_square == white_king || extra_square == black_king {
+        return None;
+    }
+
+    let mut builder = BoardBuilder::new();
+    builder
+        .piece(ALL_SQUARES[white_king], Piece::King, Color::White)
+        .piece(ALL_SQUARES[black_king], Piece::King, Color::Black)
+        .piece(ALL_SQUARES[extra_square], extra, Color::White)
+        .side_to_move(side);
+
+    Board::try_from(&builder).ok()
+}
+
+#[derive(Clone, Copy)]
+enum Child {
+    // A position of the same material.
+    Same(usize),
+    // A position of another material, with its result for the side to move there.
+    Known(Wdl),
+}
+
+/// The moves of every position, each with whether it zeroes or mates.
+struct Moves {
+    start: Vec<usize>,
+    moves: Vec<(Child, bool, bool)>,
+}
+
+impl Moves {
+    fn new(boards: &[Option<Board>], extra: Piece, solved: &HashMap<Piece, Solved>) -> Moves {
+        let mut start = vec![0];
+        let mut moves = Vec::new();
+
+        for board in boards {
+            for movement in board.iter().flat_map(MoveGen::new_legal) {
+                let board = board.expect("Only legal positions have moves");
+                let next = board.make_move_new(movement);
+
+                let child = if next.combined().popcnt() == 2 {
+                    Child::Known(Wdl::Draw)
+                } else if let Some(promotion) = movement.get_promotion() {
+                    Child::Known(solved[&promotion].wdl[id_of(&next, promotion)])
+                } else {
+                    Child::Same(id_of(&next, extra))
+                };
+
+                let mate = next.status() == BoardStatus::Checkmate;
+                moves.push((child, is_zeroing(&board, movement), mate));
+            }
+
+            start.push(moves.len());
+        }
+
+        Moves { start, moves }
+    }
+
+    fn of(&self, id: usize) -> &[(Child, bool, bool)] {
+        &self.moves[self.start[id]..self.start[id + 1]]
+    }
+}
+
+/// The result of every position of one material, and its distance to zeroing in plies.
+struct Solved {
+    boards: Vec<Option<Board>>,
+    wdl: Vec<Wdl>,
+    dtz: Vec<i32>,
+}
+
+fn solve(extra: Piece, solved: &HashMap<Piece, Solved>) -> Solved {
+    let boards: Vec<Option<Board>> = (0..POSITIONS).map(|id| board_of(id, extra)).collect();
+    let moves = Moves::new(&boards, extra, solved);
+
+    let mut wdl: Vec<Option<Wdl>> = boards
+        .iter()
+        .map(|board| match board.map(|board| board.status()) {
+            Some(BoardStatus::Checkmate) => Some(Wdl::Loss),
+            Some(BoardStatus::Stalemate) | None => Some(Wdl::Draw),
+            Some(BoardStatus::Ongoing) => None,
+        })
+        .collect();
+
+    // A position is won by a move to a lost one, and lost if every move goes to a won one.
+    // Whatever is left once nothing changes can be held by both sides.
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for id in 0..POSITIONS {
+            if wdl[id].is_some() {
+                continue;
+            }
+
+            let results: Vec<Option<Wdl>> = moves
+                .of(id)
+                .iter()
+                .map(|&(child, _, _)| match child {
+                    Child::Same(child) => wdl[child].map(|result| -result),
+                    Child::Known(result) => Some(-result),
+                })
+                .collect();
+
+            if results.contains(&Some(Wdl::Win)) {
+                wdl[id] = Some(Wdl::Win);
+                changed = true;
+            } else if results.iter().all(|&result| result == Some(Wdl::Loss)) {
+                wdl[id] = Some(Wdl::Loss);
+                changed = true;
+            }
+        }
+    }
+    let wdl: Vec<Wdl> = wdl
+        .into_iter()
+        .map(|result| result.unwrap_or(Wdl::Draw))
+        .collect();
+
+    // The winner takes the fastest way to zero, the loser the slowest one.
+    // A capture, a pawn move or a mate zeroes in one ply.
+    let mut dtz: Vec<Option<i32>> = (0..POSITIONS)
+        .map(|id| match wdl[id] {
+            Wdl::Draw => Some(0),
+            _ => None,
+        })
+        .collect();
+
+    let mut level = 1;
+    loop {
+        let mut found = Vec::new();
+        for id in (0..POSITIONS).filter(|&id| dtz[id].is_none()) {
+            let moves = moves.of(id);
+
+            if wdl[id] == Wdl::Win {
+                let wins = moves.iter().any(|&(child, zeroing, mate)| match child {
+                    Child::Same(child) if wdl[child] == Wdl::Loss => {
+                        zeroing || mate || dtz[child] == Some(1 - level)
+                    }
+                    Child::Known(Wdl::Loss) => level == 1,
+                    _ => false,
+                });
+
+                if wins {
+                    found.push((id, level));
+                }
+            } else {
+                let longest = moves
+                    .iter()
+                    .map(|&(child, zeroing, _)| match child {
+                        Child::Same(child) if !zeroing => dtz[child].map(|dtz| dtz + 1),
+                        _ => Some(1),
+                    })
+                    .try_fold(1, |longest, plies| plies.map(|plies| plies.max(longest)));
+
+                if let Some(longest) = longest {
+                    found.push((id, -longest));
+                }
+            }
+        }
+
+        if found.is_empty() {
+            break;
+        }
+
+        for (id, plies) in found {
+            dtz[id] = Some(plies);
+        }
+        level += 1;
+    }
+
+    let dtz = dtz
+        .into_iter()
+        .map(|plies| plies.expect("Every won or lost position zeroes"))
+        .collect();
+
+    Solved { boards, wdl, dtz }
+}
+
+/// The lengths of a Huffman code for symbols that occur `counts` times.
+fn code_lengths(counts: &[usize]) -> Vec<u32> {
+    let mut parents = vec![usize::MAX; counts.len()];
+    let mut heap: BinaryHeap<Reverse<(usize, usize)>> = counts
+        .iter()
+        .enumerate()
+        .map(|(node, &count)| Reverse((count, node)))
+        .collect();
+
+    while heap.len() > 1 {
+        let Reverse((first, first_node)) = heap.pop().expect("Heap has two nodes");
+        let Reverse((second, second_node)) = heap.pop().expect("Heap has two nodes");
+
+        let node = parents.len();
+        parents.push(usize::MAX);
+        parents[first_node] = node;
+        parents[second_node] = node;
+        heap.push(Reverse((first + second, node)));
+    }
+
+    (0..counts.len())
+        .map(|mut node| {
+            let mut length = 0;
+            while parents[node] != usize::MAX {
+                node = parents[node];
+                length += 1;
+            }
+            length
+        })
+        .collect()
+}
+
+/// The sizes and the data of one side and file of a table.
+struct Written {
+    sizes: Vec<u8>,
+    sparse_index: Vec<u8>,
+    block_lengths: Vec<u8>,
+    blocks: Vec<u8>,
+}
+
+/// Compresses values with a canonical Huffman code, without pairing any of them.
+fn compress(flags: u8, values: &[u16]) -> Written {
+    let mut frequency: HashMap<u16, usize> = HashMap::new();
+    for &value in values {
+        *frequency.entry(value).or_default() += 1;
+    }
+
+    if frequency.len() == 1 {
+        return Written {
+            sizes: vec![flags | FLAG_SINGLE_VALUE, values[0] as u8],
+            sparse_index: Vec::new(),
+            block_lengths: Vec::new(),
+            blocks: Vec::new(),
+        };
+    }
+
+    // The longest codes get the lowest symbols, as the canonical code has it.
+    let mut symbols: Vec<(u16, usize)> = frequency.into_iter().collect();
+    symbols.sort_unstable();
+    let lengths = code_lengths(&symbols.iter().map(|&(_, count)| count).collect::<Vec<_>>());
+    let mut symbols: Vec<(u32, u16)> = lengths
+        .into_iter()
+        .zip(symbols)
+        .map(|(length, (value, _))| (length, value))
+        .collect();
+    symbols.sort_unstable_by_key(|&(length, value)| (Reverse(length), value));
+
+    let min_len = symbols.last().expect("Two symbols").0;
+    let max_len = symbols[0].0;
+    assert!(max_len <= 32, "Huffman code is too long");
+    let lens = (max_len - min_len + 1) as usize;
+
+    let mut count = vec![0u64; lens];
+    for &(length, _) in symbols.iter() {
+        count[(length - min_len) as usize] += 1;
+    }
+
+    let mut lowest = vec![0u64; lens];
+    let mut base = vec![0u64; lens];
+    for len in (0..lens - 1).rev() {
+        lowest[len] = lowest[len + 1] + count[len + 1];
+        base[len] = (base[len + 1] + count[len + 1]) / 2;
+    }
+
+    let mut codes: HashMap<u16, (u64, u32)> = HashMap::new();
+    for (symbol, &(length, value)) in symbols.iter().enumerate() {
+        let len = (length - min_len) as usize;
+        codes.insert(value, (base[len] + symbol as u64 - lowest[len], length));
+    }
+
+    // Every block leaves room for the eight bytes the reader fetches ahead.
+    let block_size = 1usize << BLOCK_SIZE_LOG2;
+    let block_bits = 8 * block_size - 64;
+    let mut blocks = Vec::new();
+    let mut block_starts = Vec::new();
+    let mut block_lengths = Vec::new();
+    let mut bits = block_bits;
+    let mut block = Vec::new();
+
+    for (index, value) in values.iter().enumerate() {
+        let (code, length) = codes[value];
+        if bits + length as usize > block_bits {
+            if !block_starts.is_empty() {
+                blocks.push(std::mem::take(&mut block));
+            }
+            block = vec![0u8; block_size];
+            block_starts.push(index);
+            bits = 0;
+        }
+
+        for bit in (0..length).rev() {
+            if code >> bit & 1 != 0 {
+                block[bits / 8] |= 0x80 >> (bits % 8);
+            }
+            bits += 1;
+        }
+    }
+    blocks.push(block);
+
+    for (block, &start) in block_starts.iter().enumerate() {
+        let end = block_starts.get(block + 1).copied().unwrap_or(values.len());
+        block_lengths.extend_from_slice(&((end - start - 1) as u16).to_le_bytes());
+    }
+
+    // Each entry points to the middle of its span, past the last block if need be.
+    let span = 1usize << SPAN_LOG2;
+    let mut sparse_index = Vec::new();
+    for entry in 0..values.len().div_ceil(span) {
+        let middle = entry * span + span / 2;
+        let block = block_starts.partition_point(|&start| start <= middle) - 1;
+        sparse_index.extend_from_slice(&(block as u32).to_le_bytes());
+        sparse_index.extend_from_slice(&((middle - block_starts[block]) as u16).to_le_bytes());
+    }
+
+    let mut sizes = vec![flags, BLOCK_SIZE_LOG2, SPAN_LOG2, 0];
+    sizes.extend_from_slice(&(blocks.len() as u32).to_le_bytes());
+    sizes.extend_from_slice(&[max_len as u8, min_len as u8]);
+    for &first in lowest.iter().take(lens) {
+        sizes.extend_from_slice(&(first as u16).to_le_bytes());
+    }
+
+    // Every symbol is a value, which is marked by a right half of all ones.
+    sizes.extend_from_slice(&(symbols.len() as u16).to_le_bytes());
+    for &(_, value) in symbols.iter() {
+        sizes.extend_from_slice(&[value as u8, (value >> 8) as u8 | 0xf0, 0xff]);
+    }
+    if symbols.len() % 2 == 1 {
+        sizes.push(0);
+    }
+
+    Written {
+        sizes,
+        sparse_index,
+        block_lengths,
+        blocks: blocks.concat(),
+    }
+}
+
+/// The pieces of a table in the order of its index: the leading pawn or the white king first.
+fn table_pieces(extra: Piece) -> Vec<u8> {
+    let white_king = piece_code(Piece::King, Color::White);
+    let black_king = piece_code(Piece::King, Color::Black);
+    let extra = piece_code(extra, Color::White);
+
+    if extra == piece_code(Piece::Pawn, Color::White) {
+        vec![extra, white_king, black_king]
+    } else {
+        vec![white_king, extra, black_king]
+    }
+}
+
+fn write_table(material: &Material, extra: Piece, solved: &Solved, kind: TableKind) -> Vec<u8> {
+    let pieces = table_pieces(extra);
+    let sides = match kind {
+        TableKind::Wdl => 2,
+        TableKind::Dtz => 1,
+    };
+    let encodings: Vec<Vec<Encoding>> = (0..material.files())
+        .map(|file| {
+            (0..sides)
+                .map(|_| Encoding::new(material, pieces.clone(), [0, 0xf], file))
+                .collect()
+        })
+        .collect();
+
+    let mut values: Vec<Vec<Vec<Option<u16>>>> = encodings
+        .iter()
+        .map(|file| {
+            file.iter()
+                .map(|encoding| vec![None; encoding.size() as usize])
+                .collect()
+        })
+        .collect();
+
+    for (id, board) in solved.boards.iter().enumerate() {
+        let Some(board) = board else {
+            continue;
+        };
+
+        let placement = Placement::new(material, board, false, pieces[0]);
+        if placement.side >= sides {
+            continue;
+        }
+
+        let value = match kind {
+            TableKind::Wdl => solved.wdl[id] as u16,
+            TableKind::Dtz if solved.wdl[id] == Wdl::Draw => continue,
+            TableKind::Dtz => solved.dtz[id].unsigned_abs() as u16 - 1,
+        };
+
+        let encoding = &encodings[placement.file][placement.side];
+        let index = encoding.index(material, &placement) as usize;
+        let slot = &mut values[placement.file][placement.side][index];
+        assert!(slot.is_none_or(|stored| stored == value), "{}", board);
+        *slot = Some(value);
+    }
+
+    let flags = match kind {
+        TableKind::Wdl => 0,
+        TableKind::Dtz => FLAG_WIN_PLIES | FLAG_LOSS_PLIES,
+    };
+
+    // The values of illegal positions are never probed, so they take the most common value.
+    let written: Vec<Written> = values
+        .into_iter()
+        .flatten()
+        .map(|values| {
+            let mut frequency: HashMap<u16, usize> = HashMap::new();
+            for value in values.iter().flatten() {
+                *frequency.entry(*value).or_default() += 1;
+            }
+            let common = frequency
+                .into_iter()
+                .max_by_key(|&(value, count)| (count, Reverse(value)))
+                .map_or(0, |(value, _)| value);
+
+            let values: Vec<u16> = values.iter().map(|value| value.unwrap_or(common)).collect();
+            compress(flags, &values)
+        })
+        .collect();
+
+    let mut data = kind.magic().to_vec();
+    data.push(1 | if material.has_pawns { 2 } else { 0 });
+    for _ in 0..material.files() {
+        data.push(0);
+        data.extend(pieces.iter().map(|&piece| piece | piece << 4));
+    }
+    if data.len() % 2 == 1 {
+        data.push(0);
+    }
+
+    for part in written.iter() {
+        data.extend_from_slice(&part.sizes);
+    }
+    if kind == TableKind::Dtz && data.len() % 2 == 1 {
+        data.push(0);
+    }
+    for part in written.iter() {
+        data.extend_from_slice(&part.sparse_index);
+    }
+    for part in written.iter() {
+        data.extend_from_slice(&part.block_lengths);
+    }
+    for part in written.iter() {
+        data.resize(data.len().next_multiple_of(64), 0);
+        data.extend_from_slice(&part.blocks);
+    }
+
+    data
+}
+
+fn material_name(extra: Piece) -> String {
+    let board = board_of(
+        position_id(
+            Square::A1.to_index(),
+            Square::H8.to_index(),
+            10,
+            Color::White,
+        ),
+        extra,
+    )
+    .expect("A legal board");
+    Material::name_of(&board, Color::White)
+}
+
+#[test]
+#[ignore]
+fn generate_syzygy_fixtures() {
+    fs::create_dir_all(FIXTURES).expect("Fixture directory");
+
+    let mut solved = HashMap::new();
+    for extra in EXTRA_PIECES {
+        let name = material_name(extra);
+        let material = Material::from_name(&name).expect("Known material");
+        let solution = solve(extra, &solved);
+
+        for kind in [TableKind::Wdl, TableKind::Dtz] {
+            let data = write_table(&material, extra, &solution, kind);
+            let path = Path::new(FIXTURES).join(format!("{}.{}", name, kind.extension()));
+            fs::write(&path, &data).expect("Fixture written");
+
+            // The reader has to find every value again.
+            let table = Table::read(data, material.clone(), kind).expect("Table read back");
+            for (id, board) in solution.boards.iter().enumerate() {
+                let Some(board) = board else {
+                    continue;
+                };
+
+                let wdl = solution.wdl[id];
+                match kind {
+                    TableKind::Wdl => assert_eq!(table.wdl(board), Some(wdl), "{}", board),
+                    TableKind::Dtz if wdl == Wdl::Draw || board.side_to_move() == Color::Black => {}
+                    TableKind::Dtz => assert_eq!(
+                        table.dtz(board, wdl),
+                        Some(solution.dtz[id].abs()),
+                        "{}",
+                        board
+                    ),
+                }
+            }
+        }
+
+        solved.insert(extra, solution);
+    }
+
+    // Probing resolves captures and the side that the DTZ tables leave out,
+    // and has to agree with the solution everywhere.
+    let tablebases = Tablebases::open(FIXTURES);
+    for (&extra, solution) in solved.iter() {
+        for (id, board) in solution.boards.iter().enumerate() {
+            let Some(board) = board else {
+                continue;
+            };
+
+            assert_eq!(
+                tablebases.probe_wdl(board),
+                Some(solution.wdl[id]),
+                "{}",
+                board
+            );
+            assert_eq!(
+                tablebases.probe_dtz(board),
+                Some(solution.dtz[id]),
+                "{} {:?}",
+                board,
+                extra
+            );
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use chess::Board;
+
+use crate::uci::go_options::GoOptions;
+use crate::uci::options::UCIOptions;
+
+/// The optimum and maximum time of a `go` command, for white to move.
+fn limits(go: &str, options: &UCIOptions) -> (Duration, Duration) {
+    let deadline = GoOptions::build(go).to_deadline(&Board::default(), options);
+    (
+        deadline.optimum().expect("Expected an optimum time"),
+        deadline.maximum().expect("Expected a maximum time"),
+    )
+}
+
+fn half_of_clock(time: u64, options: &UCIOptions) -> Duration {
+    (Duration::from_millis(time) - options.move_overhead()) / 2
+}
+
+#[test]
+fn last_move_before_time_control_uses_at_most_half() {
+    let options = UCIOptions::new();
+    let (optimum, maximum) = limits("wtime 10000 btime 10000 movestogo 1", &options);
+
+    assert!(optimum <= half_of_clock(10000, &options));
+    assert!(maximum <= half_of_clock(10000, &options));
+}
+
+#[test]
+fn ponder_bonus_uses_at_most_half() {
+    let mut options = UCIOptions::new();
+    options.set("Ponder", "true").unwrap();
+    let (optimum, maximum) = limits("wtime 10000 btime 10000 movestogo 1", &options);
+
+    assert!(optimum <= half_of_clock(10000, &options));
+    assert!(maximum <= half_of_clock(10000, &options));
+}
+
+#[test]
+fn large_increment_uses_at_most_half() {
+    let options = UCIOptions::new();
+    let (optimum, maximum) = limits("wtime 2000 btime 2000 winc 8000 binc 8000", &options);
+
+    assert!(optimum <= half_of_clock(2000, &options));
+    assert!(maximum <= half_of_clock(2000, &options));
+}
+
+#[test]
+fn maximum_is_never_below_optimum() {
+    let options = UCIOptions::new();
+    let (optimum, maximum) = limits("wtime 60000 btime 60000 movestogo 30", &options);
+
+    assert!(optimum <= maximum);
+    assert!(optimum > Duration::ZERO);
+}
@@ -96,12 +96,41 @@ impl BestMoves {
         }
     }
 
-    pub fn marked(&self) -> Self {
+    pub fn rotated(&self, count: usize) -> Self {
+        match *self {
+            Self::Best2(b1, b2) if count % 2 == 1 => Self::Best2(b2, b1),
+            Self::Best3(b1, b2, b3) if count % 3 == 1 => Self::Best3(b2, b3, b1),
+            Self::Best3(b1, b2, b3) if count % 3 == 2 => Self::Best3(b3, b1, b2),
+            _ => *self,
+        }
+    }
+
+    pub fn filtered(&self, mut keep: impl FnMut(ChessMove) -> bool) -> Self {
+        let mut moves = *self;
+        let mut filtered = Self::Empty;
+
+        while let Some(rated) = moves.pop() {
+            if keep(rated.mv) {
+                filtered = match filtered {
+                    Self::Empty => Self::Best1(rated),
+                    Self::Best1(b1) => Self::Best2(b1, rated),
+                    Self::Best2(b1, b2) => Self::Best3(b1, b2, rated),
+                    Self::Best3(..) => filtered,
+                };
+            }
+        }
+
+        filtered
+    }
+
+    pub fn map_scores(&self, f: impl Fn(Score) -> Score) -> Self {
         match self {
             Self::Empty => Self::Empty,
-            Self::Best1(b1, ..) => Self::Best1(b1.mark()),
-            Self::Best2(b1, b2, ..) => Self::Best2(b1.mark(), b2.mark()),
-            Self::Best3(b1, b2, b3, ..) => Self::Best3(b1.mark(), b2.mark(), b3.mark()),
+            Self::Best1(b1, ..) => Self::Best1(b1.map_score(&f)),
+            Self::Best2(b1, b2, ..) => Self::Best2(b1.map_score(&f), b2.map_score(&f)),
+            Self::Best3(b1, b2, b3, ..) => {
+                Self::Best3(b1.map_score(&f), b2.map_score(&f), b3.map_score(&f))
+            }
         }
     }
 }
@@ -1,5 +1,5 @@
 pub mod best_moves;
-pub mod pv_line;
+pub mod packed_entry;
 pub mod rated_move;
 pub mod table;
 pub mod table_entry;
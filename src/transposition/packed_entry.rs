@@ -0,0 +1,277 @@
+use chess::{ChessMove, Piece, ALL_SQUARES};
+
+use crate::evaluate::{Score, CENTIPAWN, MATE, MATE_CUTOFF, MATE_MOVE};
+use crate::search::{Depth, DEPTH_EDGE, DEPTH_LEAF};
+use crate::transposition::best_moves::BestMoves;
+use crate::transposition::rated_move::RatedMove;
+use crate::transposition::table_entry::TTableEntry;
+
+// Evaluations are a weighted average over the 24 steps of the game phase,
+// so every score the search produces is a multiple of this grain.
+const SCORE_GRAIN: Score = CENTIPAWN / 24;
+
+// Packed values this close to the limit of their field hold the
+// number of moves to mate instead of a score.
+const MATE_RANGE: i64 = (MATE - MATE_CUTOFF) / MATE_MOVE;
+
+const EMPTY: u8 = 0;
+const EXACT_NODE: u8 = 1;
+const LOWER_NODE: u8 = 2;
+const UPPER_NODE: u8 = 3;
+const NULL_CUT: u8 = 4;
+const EDGE: u8 = 5;
+const LEAF: u8 = 6;
+
+// The widths of the fields once the entry is packed into two words.
+// A move needs 15 bits: two squares and the promotion piece.
+const SCORE_BITS: u32 = 24;
+const GAP_BITS: u32 = 24;
+const MOVE_BITS: u32 = 15;
+const KIND_BITS: u32 = 3;
+const DEPTH_BITS: u32 = 8;
+
+const _: () = assert!(SCORE_BITS + 2 * GAP_BITS + 3 * MOVE_BITS + KIND_BITS + DEPTH_BITS == 128);
+
+const SCORE_LIMIT: i64 = (1 << (SCORE_BITS - 1)) - 1;
+
+/// A transposition table entry squeezed into two words.
+///
+/// The moves, the bound, the depth and the scores are kept exactly,
+/// as long as the scores stay within 3,400 pawns of even.
+/// The gap fields are wide enough to hold the distance between any two such scores,
+/// so no move is ever clamped against the best one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PackedEntry {
+    score: i32,
+    gaps: [u32; 2],
+    moves: [u16; 3],
+    kind: u8,
+    depth: u8,
+}
+
+fn pack_score(score: Score, limit: i64) -> i64 {
+    if score >= MATE_CUTOFF {
+        limit - (MATE - score) / MATE_MOVE
+    } else if score <= -MATE_CUTOFF {
+        -limit + (MATE + score) / MATE_MOVE
+    } else {
+        let bound = limit - MATE_RANGE - 1;
+        (score / SCORE_GRAIN).clamp(-bound, bound)
+    }
+}
+
+fn unpack_score(packed: i64, limit: i64) -> Score {
+    if packed >= limit - MATE_RANGE {
+        MATE - (limit - packed) * MATE_MOVE
+    } else if packed <= -limit + MATE_RANGE {
+        -MATE + (limit + packed) * MATE_MOVE
+    } else {
+        packed * SCORE_GRAIN
+    }
+}
+
+// Below a mate score, the other scores are packed on their own like the best score.
+// Otherwise they are packed as their gap below the best score, with the top of
+// the range holding the number of moves until mate for moves that get mated.
+// The regular gaps reach twice the score limit, so they never need clamping.
+const GAP_LIMIT: i64 = (1 << GAP_BITS) - 1;
+
+const _: () = assert!(GAP_LIMIT - MATE_RANGE > 2 * (SCORE_LIMIT - MATE_RANGE - 1));
+
+fn pack_gap(best: Score, score: Score) -> u32 {
+    if best.abs() >= MATE_CUTOFF {
+        pack_score(score, SCORE_LIMIT) as u32 & GAP_LIMIT as u32
+    } else if score <= -MATE_CUTOFF {
+        (GAP_LIMIT - (MATE + score) / MATE_MOVE) as u32
+    } else {
+        ((best - score) / SCORE_GRAIN).clamp(0, GAP_LIMIT - MATE_RANGE - 1) as u32
+    }
+}
+
+fn unpack_gap(best: Score, gap: u32) -> Score {
+    if best.abs() >= MATE_CUTOFF {
+        unpack_score(sign_extend(gap as u64, GAP_BITS), SCORE_LIMIT)
+    } else {
+        let gap = gap as i64;
+        if gap >= GAP_LIMIT - MATE_RANGE {
+            -MATE + (GAP_LIMIT - gap) * MATE_MOVE
+        } else {
+            best - gap * SCORE_GRAIN
+        }
+    }
+}
+
+fn sign_extend(bits: u64, width: u32) -> i64 {
+    ((bits << (64 - width)) as i64) >> (64 - width)
+}
+
+fn mask(width: u32) -> u128 {
+    (1 << width) - 1
+}
+
+fn pack_move(mv: ChessMove) -> u16 {
+    let promotion = match mv.get_promotion() {
+        Some(Piece::Knight) => 1,
+        Some(Piece::Bishop) => 2,
+        Some(Piece::Rook) => 3,
+        Some(Piece::Queen) => 4,
+        _ => 0,
+    };
+
+    mv.get_source().to_index() as u16 | (mv.get_dest().to_index() as u16) << 6 | promotion << 12
+}
+
+fn unpack_move(packed: u16) -> Option<ChessMove> {
+    // a1a1 is never a legal move, so it marks an empty move slot.
+    if packed == 0 {
+        return None;
+    }
+
+    let promotion = match packed >> 12 {
+        1 => Some(Piece::Knight),
+        2 => Some(Piece::Bishop),
+        3 => Some(Piece::Rook),
+        4 => Some(Piece::Queen),
+        _ => None,
+    };
+
+    Some(ChessMove::new(
+        ALL_SQUARES[(packed & 0x3f) as usize],
+        ALL_SQUARES[(packed >> 6 & 0x3f) as usize],
+        promotion,
+    ))
+}
+
+impl PackedEntry {
+    pub fn is_empty(&self) -> bool {
+        self.kind == EMPTY
+    }
+
+    pub fn depth(&self) -> Depth {
+        match self.kind {
+            EDGE => DEPTH_EDGE,
+            LEAF => DEPTH_LEAF,
+            _ => self.depth as Depth,
+        }
+    }
+
+    fn pack(kind: u8, depth: Depth, score: Score) -> PackedEntry {
+        PackedEntry {
+            score: pack_score(score, SCORE_LIMIT) as i32,
+            gaps: [0; 2],
+            moves: [0; 3],
+            kind,
+            depth: depth.clamp(0, u8::MAX as Depth) as u8,
+        }
+    }
+
+    fn pack_moves(kind: u8, depth: Depth, moves: &BestMoves) -> PackedEntry {
+        let best = moves.score();
+        let mut packed = Self::pack(kind, depth, best);
+        let mut moves = *moves;
+        let mut index = 0;
+
+        while let Some(rated) = moves.pop() {
+            packed.moves[index] = pack_move(rated.mv);
+            if index > 0 {
+                packed.gaps[index - 1] = pack_gap(best, rated.score);
+            }
+
+            index += 1;
+        }
+
+        packed
+    }
+
+    fn unpack_moves(&self) -> BestMoves {
+        let best = unpack_score(self.score as i64, SCORE_LIMIT);
+        let rated = |index: usize| {
+            let score = if index == 0 {
+                best
+            } else {
+                unpack_gap(best, self.gaps[index - 1])
+            };
+
+            unpack_move(self.moves[index]).map(|mv| RatedMove::new(score, mv))
+        };
+
+        match (rated(0), rated(1), rated(2)) {
+            (Some(b1), Some(b2), Some(b3)) => BestMoves::Best3(b1, b2, b3),
+            (Some(b1), Some(b2), None) => BestMoves::Best2(b1, b2),
+            (Some(b1), None, None) => BestMoves::Best1(b1),
+            _ => BestMoves::Empty,
+        }
+    }
+
+    /// The entry as two words, which the table stores without a lock.
+    pub fn to_words(self) -> [u64; 2] {
+        let fields = [
+            (self.score as u128, SCORE_BITS),
+            (self.gaps[0] as u128, GAP_BITS),
+            (self.gaps[1] as u128, GAP_BITS),
+            (self.moves[0] as u128, MOVE_BITS),
+            (self.moves[1] as u128, MOVE_BITS),
+            (self.moves[2] as u128, MOVE_BITS),
+            (self.kind as u128, KIND_BITS),
+            (self.depth as u128, DEPTH_BITS),
+        ];
+
+        let mut bits = 0;
+        let mut offset = 0;
+        for (value, width) in fields {
+            bits |= (value & mask(width)) << offset;
+            offset += width;
+        }
+
+        [bits as u64, (bits >> 64) as u64]
+    }
+
+    pub fn from_words(words: [u64; 2]) -> PackedEntry {
+        let mut bits = words[0] as u128 | (words[1] as u128) << 64;
+        let mut field = |width: u32| {
+            let value = bits & mask(width);
+            bits >>= width;
+            value as u64
+        };
+
+        PackedEntry {
+            score: sign_extend(field(SCORE_BITS), SCORE_BITS) as i32,
+            gaps: [field(GAP_BITS) as u32, field(GAP_BITS) as u32],
+            moves: [
+                field(MOVE_BITS) as u16,
+                field(MOVE_BITS) as u16,
+                field(MOVE_BITS) as u16,
+            ],
+            kind: field(KIND_BITS) as u8,
+            depth: field(DEPTH_BITS) as u8,
+        }
+    }
+
+    pub fn unpack(&self) -> Option<TTableEntry> {
+        let depth = self.depth as Depth;
+        let score = unpack_score(self.score as i64, SCORE_LIMIT);
+
+        match self.kind {
+            EXACT_NODE => Some(TTableEntry::ExactNode(depth, self.unpack_moves())),
+            LOWER_NODE => Some(TTableEntry::LowerNode(depth, self.unpack_moves())),
+            UPPER_NODE => Some(TTableEntry::UpperNode(depth, self.unpack_moves())),
+            NULL_CUT => Some(TTableEntry::NullCut(depth, score)),
+            EDGE => Some(TTableEntry::Edge(score)),
+            LEAF => Some(TTableEntry::Leaf(score)),
+            _ => None,
+        }
+    }
+}
+
+impl From<TTableEntry> for PackedEntry {
+    fn from(entry: TTableEntry) -> Self {
+        match entry {
+            TTableEntry::ExactNode(depth, moves) => Self::pack_moves(EXACT_NODE, depth, &moves),
+            TTableEntry::LowerNode(depth, moves) => Self::pack_moves(LOWER_NODE, depth, &moves),
+            TTableEntry::UpperNode(depth, moves) => Self::pack_moves(UPPER_NODE, depth, &moves),
+            TTableEntry::NullCut(depth, score) => Self::pack(NULL_CUT, depth, score),
+            TTableEntry::Edge(score) => Self::pack(EDGE, 0, score),
+            TTableEntry::Leaf(score) => Self::pack(LEAF, 0, score),
+        }
+    }
+}
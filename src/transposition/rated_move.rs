@@ -1,6 +1,6 @@
 use chess::ChessMove;
 
-use crate::evaluate::{score_mark, Score};
+use crate::evaluate::Score;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct RatedMove {
@@ -13,9 +13,9 @@ impl RatedMove {
         Self { score, mv }
     }
 
-    pub fn mark(&self) -> Self {
+    pub fn map_score(&self, f: impl Fn(Score) -> Score) -> Self {
         Self {
-            score: score_mark(self.score),
+            score: f(self.score),
             mv: self.mv,
         }
     }
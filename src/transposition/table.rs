@@ -1,33 +1,30 @@
+use std::alloc::{self, Layout};
 use std::mem::size_of;
-use std::num::NonZeroUsize;
+use std::ptr;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 
-use chess::Board;
-use lru::LruCache;
-use nohash_hasher::BuildNoHashHasher;
+use chess::{Board, ChessMove};
 
 use crate::search::alpha_beta::AlphaBeta;
-use crate::search::Depth;
+use crate::search::{Depth, DEPTH_EDGE, DEPTH_LEAF};
 
 use crate::transposition::best_moves::BestMoves;
-use crate::transposition::pv_line::PVLine;
+use crate::transposition::packed_entry::PackedEntry;
 use crate::transposition::table_entry::TTableEntry;
 
-const ELEMENT_SIZE: usize = size_of::<*const TTableKey>()
-    + size_of::<TTableKey>()
-    + size_of::<TTableEntry>()
-    + 2 * size_of::<*const u64>()
-    + size_of::<u64>();
+const BUCKET_SIZE: usize = 4;
 
-const PV_TABLE_SIZE: usize = 1024;
+const PV_TABLE_BUCKETS: usize = 256;
 
-use TTableEntry::*;
+// How many plies of depth an entry is worth per search it has survived.
+const AGE_WEIGHT: Depth = 4;
 
-pub type TTableKey = u64;
+// Edge entries are exact at any depth, but they should still age out eventually.
+const MAX_PRIORITY_DEPTH: Depth = 256;
 
-pub type TableHashBuilder = BuildNoHashHasher<u64>;
+use TTableEntry::*;
 
-pub type TTableHashMap = LruCache<TTableKey, TTableEntry, TableHashBuilder>;
-pub type PVTableHashMap = LruCache<TTableKey, TTableEntry, TableHashBuilder>;
+pub type TTableKey = u64;
 
 pub enum TTableSample {
     None,
@@ -35,28 +32,204 @@ pub enum TTableSample {
     Score(TTableEntry),
 }
 
+// A slot holds the key mixed into the two words of its entry, so that it can be
+// read and written without a lock. An entry torn by two threads writing it at
+// once no longer matches its key, and is read as a miss.
+struct TTableSlot {
+    check: AtomicU64,
+    words: [AtomicU64; 2],
+}
+
+impl TTableSlot {
+    #[inline]
+    fn load(&self) -> (TTableKey, PackedEntry) {
+        let words = [
+            self.words[0].load(Ordering::Relaxed),
+            self.words[1].load(Ordering::Relaxed),
+        ];
+        let key = self.check.load(Ordering::Relaxed) ^ words[0] ^ words[1];
+        (key, PackedEntry::from_words(words))
+    }
+
+    #[inline]
+    fn save(&self, key: TTableKey, entry: PackedEntry) {
+        let words = entry.to_words();
+        self.check
+            .store(key ^ words[0] ^ words[1], Ordering::Relaxed);
+        self.words[0].store(words[0], Ordering::Relaxed);
+        self.words[1].store(words[1], Ordering::Relaxed);
+    }
+}
+
+// Every field is an atomic integer, so a bucket of zero bytes is a valid empty bucket.
+struct TTableBucket {
+    slots: [TTableSlot; BUCKET_SIZE],
+    generations: [AtomicU8; BUCKET_SIZE],
+}
+
+impl TTableBucket {
+    fn find(&self, key: TTableKey) -> Option<(usize, PackedEntry)> {
+        (0..BUCKET_SIZE).find_map(|index| match self.slots[index].load() {
+            (found, entry) if found == key && !entry.is_empty() => Some((index, entry)),
+            _ => None,
+        })
+    }
+
+    fn generation(&self, index: usize) -> u8 {
+        self.generations[index].load(Ordering::Relaxed)
+    }
+
+    fn refresh(&self, index: usize, generation: u8) {
+        self.generations[index].store(generation, Ordering::Relaxed);
+    }
+
+    fn priority(&self, index: usize, generation: u8) -> Depth {
+        let (_, entry) = self.slots[index].load();
+        let age = generation.wrapping_sub(self.generation(index)) as Depth;
+        entry.depth().min(MAX_PRIORITY_DEPTH) - AGE_WEIGHT * age
+    }
+}
+
+struct TTableBuckets {
+    buckets: Box<[TTableBucket]>,
+}
+
+impl TTableBuckets {
+    fn new(bucket_count: usize) -> TTableBuckets {
+        let mut bucket_count = 1 << bucket_count.max(1).ilog2();
+
+        // A table larger than the memory we can get is shrunk until it fits.
+        loop {
+            if let Some(buckets) = Self::zeroed(bucket_count) {
+                return TTableBuckets { buckets };
+            }
+
+            if bucket_count == 1 {
+                alloc::handle_alloc_error(Layout::new::<TTableBucket>());
+            }
+            bucket_count /= 2;
+        }
+    }
+
+    fn with_size(table_size: usize) -> TTableBuckets {
+        Self::new(table_size / size_of::<TTableBucket>())
+    }
+
+    /// Allocates the buckets as zeroed memory, which the system only commits
+    /// once a bucket is written, so an empty table costs next to nothing.
+    fn zeroed(bucket_count: usize) -> Option<Box<[TTableBucket]>> {
+        let layout = Layout::array::<TTableBucket>(bucket_count).ok()?;
+
+        // SAFETY: the layout is not empty, as there is at least one bucket,
+        // and all zero bytes are a valid bucket, as it only holds atomic integers.
+        unsafe {
+            let buckets = alloc::alloc_zeroed(layout) as *mut TTableBucket;
+            if buckets.is_null() {
+                return None;
+            }
+
+            Some(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                buckets,
+                bucket_count,
+            )))
+        }
+    }
+
+    #[inline]
+    fn bucket(&self, key: TTableKey) -> &TTableBucket {
+        let index = key as usize & (self.buckets.len() - 1);
+        &self.buckets[index]
+    }
+
+    fn probe(&self, key: TTableKey, refresh: Option<u8>) -> Option<TTableEntry> {
+        let bucket = self.bucket(key);
+        let (index, entry) = bucket.find(key)?;
+
+        if let Some(generation) = refresh {
+            bucket.refresh(index, generation);
+        }
+
+        entry.unpack()
+    }
+
+    /// Stores the entry, unless the table holds this position from
+    /// a deeper search, that was stored during the same search.
+    fn store(&self, key: TTableKey, entry: TTableEntry, generation: u8) {
+        self.store_over(key, entry, generation, entry.depth());
+    }
+
+    /// Stores the entry, unless the table holds this position from
+    /// a search that went deeper than `max_depth` during the same search.
+    fn store_over(&self, key: TTableKey, entry: TTableEntry, generation: u8, max_depth: Depth) {
+        let bucket = self.bucket(key);
+        let found = bucket.find(key);
+
+        if let Some((index, stored)) = found {
+            if stored.depth() > max_depth && bucket.generation(index) == generation {
+                return;
+            }
+        }
+
+        // Prefer the slot already holding this position, then an empty slot,
+        // and otherwise replace the shallowest and oldest entry in the bucket.
+        let victim = found.map(|(index, _)| index).unwrap_or_else(|| {
+            (0..BUCKET_SIZE)
+                .find(|&index| bucket.slots[index].load().1.is_empty())
+                .unwrap_or_else(|| {
+                    (0..BUCKET_SIZE)
+                        .min_by_key(|&index| bucket.priority(index, generation))
+                        .unwrap()
+                })
+        });
+
+        bucket.refresh(victim, generation);
+        bucket.slots[victim].save(key, PackedEntry::from(entry));
+    }
+
+    fn hashfull_permille(&self, generation: u8) -> usize {
+        let sample = &self.buckets[..self.buckets.len().min(1000)];
+        let used: usize = sample
+            .iter()
+            .map(|bucket| {
+                (0..BUCKET_SIZE)
+                    .filter(|&index| {
+                        !bucket.slots[index].load().1.is_empty()
+                            && bucket.generation(index) == generation
+                    })
+                    .count()
+            })
+            .sum();
+
+        used * 1000 / (sample.len() * BUCKET_SIZE)
+    }
+
+    fn memory_bytes(&self) -> usize {
+        self.buckets.len() * size_of::<TTableBucket>()
+    }
+}
+
 pub struct TTable {
-    table: TTableHashMap,
-    pv_table: PVTableHashMap,
+    table: TTableBuckets,
+    pv_table: TTableBuckets,
+    generation: AtomicU8,
 }
 
 impl TTable {
     pub fn new(table_size: usize) -> TTable {
         TTable {
-            table: TTableHashMap::with_hasher(
-                NonZeroUsize::new((table_size / ELEMENT_SIZE - 1).next_power_of_two()).unwrap(),
-                TableHashBuilder::default(),
-            ),
-            pv_table: PVTableHashMap::with_hasher(
-                NonZeroUsize::new(PV_TABLE_SIZE).unwrap(),
-                TableHashBuilder::default(),
-            ),
+            table: TTableBuckets::with_size(table_size),
+            pv_table: TTableBuckets::new(PV_TABLE_BUCKETS),
+            generation: AtomicU8::new(0),
         }
     }
 
-    pub fn set_table_size(&mut self, table_size: usize) {
-        self.table
-            .resize(NonZeroUsize::new(table_size / ELEMENT_SIZE).unwrap())
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed)
     }
 
     #[inline]
@@ -64,36 +237,71 @@ impl TTable {
         board.get_hash()
     }
 
-    pub fn update<const PV: bool>(&mut self, board: &Board, result: TTableEntry) {
+    /// Stores the result of a search of the board at `ply`.
+    pub fn update<const PV: bool>(&self, board: &Board, ply: Depth, result: TTableEntry) {
         let key = Self::to_key(board);
+        let result = result.node_relative(ply);
         if PV {
-            self.table.put(key, result);
-            self.pv_table.put(key, result);
+            self.table.store(key, result, self.generation());
+            self.pv_table.store(key, result, self.generation());
         } else {
-            self.table.put(key, result);
+            self.table.store(key, result, self.generation());
+        }
+    }
+
+    /// Stores the result, even over a deeper search of the same position.
+    pub fn replace<const PV: bool>(&self, board: &Board, ply: Depth, result: TTableEntry) {
+        let key = Self::to_key(board);
+        let result = result.node_relative(ply);
+        self.table
+            .store_over(key, result, self.generation(), DEPTH_EDGE);
+        if PV {
+            self.pv_table
+                .store_over(key, result, self.generation(), DEPTH_EDGE);
         }
     }
 
-    pub fn peek(&self, board: &Board) -> Option<&TTableEntry> {
+    /// Stores the result of a quiescence search, which must not
+    /// replace a search of the same position with depth left.
+    pub fn update_leaf(&self, board: &Board, ply: Depth, result: TTableEntry) {
+        let key = Self::to_key(board);
+        self.table.store_over(
+            key,
+            result.node_relative(ply),
+            self.generation(),
+            DEPTH_LEAF,
+        );
+    }
+
+    pub fn peek(&self, board: &Board) -> Option<TTableEntry> {
         let key = Self::to_key(board);
-        self.table.peek(&key).or_else(|| self.pv_table.peek(&key))
+        self.table
+            .probe(key, None)
+            .or_else(|| self.pv_table.probe(key, None))
     }
 
-    pub fn get<const PV: bool>(&mut self, board: &Board) -> Option<&TTableEntry> {
+    /// Reads the entry of the board, and marks it as used by this search.
+    pub fn get<const PV: bool>(&self, board: &Board) -> Option<TTableEntry> {
         let key = Self::to_key(board);
-        self.table.get(&key).or(self.pv_table.get(&key))
+        let generation = Some(self.generation());
+        self.table
+            .probe(key, generation)
+            .or_else(|| self.pv_table.probe(key, generation))
     }
 
     pub fn sample<const PV: bool>(
-        &mut self,
+        &self,
         board: &Board,
         window: &AlphaBeta,
         depth: Depth,
     ) -> TTableSample {
-        match self.peek(board).cloned() {
+        // A hit only refreshes the age of the entry, there is nothing new to store.
+        match self
+            .get::<PV>(board)
+            .map(|entry| entry.root_relative(window.ply))
+        {
             Some(sample @ ExactNode(sample_depth, moves)) => {
                 if depth <= sample_depth {
-                    self.update::<PV>(board, sample);
                     TTableSample::Score(sample)
                 } else {
                     TTableSample::Moves(moves)
@@ -101,7 +309,6 @@ impl TTable {
             }
             Some(sample @ LowerNode(sample_depth, moves)) => {
                 if depth <= sample_depth && window.beta <= moves.score() {
-                    self.update::<PV>(board, sample);
                     TTableSample::Score(sample)
                 } else {
                     TTableSample::Moves(moves)
@@ -109,7 +316,6 @@ impl TTable {
             }
             Some(sample @ UpperNode(sample_depth, moves)) => {
                 if depth <= sample_depth && moves.score() <= window.alpha {
-                    self.update::<PV>(board, sample);
                     TTableSample::Score(sample)
                 } else {
                     TTableSample::Moves(moves)
@@ -117,19 +323,14 @@ impl TTable {
             }
             Some(sample @ NullCut(sample_depth, score)) => {
                 if depth <= sample_depth && window.beta <= score {
-                    self.update::<PV>(board, sample);
                     TTableSample::Score(sample)
                 } else {
                     TTableSample::None
                 }
             }
-            Some(sample @ Edge(..)) => {
-                self.update::<PV>(board, sample);
-                TTableSample::Score(sample)
-            }
+            Some(sample @ Edge(..)) => TTableSample::Score(sample),
             Some(sample @ Leaf(..)) => {
                 if depth <= 0 {
-                    self.update::<PV>(board, sample);
                     TTableSample::Score(sample)
                 } else {
                     TTableSample::None
@@ -139,22 +340,21 @@ impl TTable {
         }
     }
 
-    pub fn promote_pv_line(&mut self, board: &Board) {
-        for _move in self.get_pv_line(board) {
-            // This loop promotes the PV line to the top
-            // of the cache just by iterating over it.
+    /// Refreshes the age of the entries along a principal variation,
+    /// so that the next search finds them again.
+    pub fn promote_pv_line(&self, board: &Board, pv: &[ChessMove]) {
+        let mut board = *board;
+        for &movement in pv {
+            self.get::<true>(&board);
+            board = board.make_move_new(movement);
         }
     }
 
-    pub fn get_pv_line(&mut self, board: &Board) -> PVLine {
-        PVLine::new(self, *board)
-    }
-
     pub fn hashfull_permille(&self) -> usize {
-        self.table.len() * 1000 / self.table.cap()
+        self.table.hashfull_permille(self.generation())
     }
 
     pub fn memory_bytes(&self) -> usize {
-        self.table.len() * ELEMENT_SIZE
+        self.table.memory_bytes() + self.pv_table.memory_bytes()
     }
 }
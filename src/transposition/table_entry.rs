@@ -1,6 +1,6 @@
 use chess::ChessMove;
 
-use crate::evaluate::{score_mark, Score};
+use crate::evaluate::{score_from_node, score_to_node, Score};
 use crate::search::{Depth, DEPTH_EDGE, DEPTH_LEAF};
 
 use super::best_moves::BestMoves;
@@ -27,15 +27,32 @@ impl TTableEntry {
         }
     }
 
-    pub fn mark(&self) -> Result<Self, ()> {
-        Ok(match self {
-            TTableEntry::ExactNode(depth, moves) => TTableEntry::ExactNode(*depth, moves.marked()),
-            TTableEntry::LowerNode(depth, moves) => TTableEntry::LowerNode(*depth, moves.marked()),
-            TTableEntry::UpperNode(depth, moves) => TTableEntry::UpperNode(*depth, moves.marked()),
-            TTableEntry::NullCut(depth, score) => TTableEntry::NullCut(*depth, score_mark(*score)),
-            TTableEntry::Edge(score) => TTableEntry::Edge(score_mark(*score)),
-            TTableEntry::Leaf(score) => TTableEntry::Leaf(score_mark(*score)),
-        })
+    pub fn map_scores(&self, f: impl Fn(Score) -> Score) -> Self {
+        match self {
+            TTableEntry::ExactNode(depth, moves) => {
+                TTableEntry::ExactNode(*depth, moves.map_scores(f))
+            }
+            TTableEntry::LowerNode(depth, moves) => {
+                TTableEntry::LowerNode(*depth, moves.map_scores(f))
+            }
+            TTableEntry::UpperNode(depth, moves) => {
+                TTableEntry::UpperNode(*depth, moves.map_scores(f))
+            }
+            TTableEntry::NullCut(depth, score) => TTableEntry::NullCut(*depth, f(*score)),
+            TTableEntry::Edge(score) => TTableEntry::Edge(f(*score)),
+            TTableEntry::Leaf(score) => TTableEntry::Leaf(f(*score)),
+        }
+    }
+
+    /// The same entry with its mate scores counted from its own position,
+    /// which is how the table keeps them, as it is reached at many plies.
+    pub fn node_relative(&self, ply: Depth) -> Self {
+        self.map_scores(|score| score_to_node(score, ply))
+    }
+
+    /// The same entry with its mate scores counted from the root again.
+    pub fn root_relative(&self, ply: Depth) -> Self {
+        self.map_scores(|score| score_from_node(score, ply))
     }
 
     pub fn depth(&self) -> Depth {
@@ -1,9 +1,13 @@
 use std::io::{self, Write};
 use std::time::Instant;
 
+use chess::ChessMove;
+
 use crate::evaluate::score_to_str;
 use crate::search::board_chain::BoardChain;
 use crate::search::engine::Engine;
+use crate::syzygy::probe::Tablebases;
+use crate::transposition::table_entry::TTableEntry;
 
 pub fn stdout_sync() {
     let _ = io::stdout().flush();
@@ -32,47 +36,92 @@ macro_rules! uci_end {
 }
 
 pub fn board_information(engine: &mut Engine, history: &BoardChain, search_start: Instant) {
-    let board_info = engine.min_search(history);
+    let lines = engine.get_lines(history);
+    let pvs: Vec<&[ChessMove]> = (0..lines.len()).map(|index| engine.get_pv(index)).collect();
+    lines_information(engine, search_start, &lines, &pvs);
+}
+
+/// Reports a search of the root that failed its aspiration window.
+pub fn bound_information(engine: &Engine, search_start: Instant, result: TTableEntry) {
+    lines_information(engine, search_start, &[result], &[engine.get_root_pv()]);
+}
 
+fn lines_information(
+    engine: &Engine,
+    search_start: Instant,
+    lines: &[TTableEntry],
+    pvs: &[&[ChessMove]],
+) {
     let time_nano = search_start.elapsed().as_nanos() as usize + 1;
 
-    let depth = board_info.depth();
-    let seldepth = engine.get_pv_line(history.last()).count();
-    let multipv = 1;
-    let score = score_to_str(board_info.score());
     let nodes = 1 + engine.get_node_count();
     let nps = nodes * 1_000_000_000 / time_nano;
     let hashfull = engine.table.hashfull_permille();
-    let tbhits = 0;
+    let tbhits = engine.get_tb_hits();
     let time = time_nano / 1_000_000;
+    let seldepth = engine.get_seldepth();
+
+    for (index, (board_info, line_pv)) in lines.iter().zip(pvs).enumerate() {
+        // Before the first iteration, or after failing low, the search has no line
+        // that starts with the best move, so the move is reported on its own.
+        let pv: Vec<ChessMove> = match board_info.peek() {
+            Some(root_move) if line_pv.first() == Some(&root_move) => line_pv.to_vec(),
+            Some(root_move) => vec![root_move],
+            None => Vec::new(),
+        };
+
+        let depth = board_info.depth();
+        let multipv = index + 1;
+        let score = score_to_str(board_info.score());
 
-    uci_token!("info");
-    uci_variable!(depth);
-    uci_variable!(seldepth);
-    uci_variable!(multipv);
-    uci_variable!(score);
-    uci_variable!(nodes);
-    uci_variable!(nps);
-    uci_variable!(hashfull);
-    uci_variable!(tbhits);
-    uci_variable!(time);
-
-    uci_token!("pv");
-    for movement in engine.get_pv_line(history.last()) {
-        uci_token!(movement);
+        uci_token!("info");
+        uci_variable!(depth);
+        uci_variable!(seldepth);
+        uci_variable!(multipv);
+        uci_variable!(score);
+        match board_info {
+            TTableEntry::LowerNode(..) => uci_token!("lowerbound"),
+            TTableEntry::UpperNode(..) => uci_token!("upperbound"),
+            _ => {}
+        }
+        uci_variable!(nodes);
+        uci_variable!(nps);
+        uci_variable!(hashfull);
+        uci_variable!(tbhits);
+        uci_variable!(time);
+
+        uci_token!("pv");
+        for movement in pv {
+            uci_token!(movement);
+        }
+
+        uci_end!();
     }
+}
 
-    uci_end!();
+pub fn tablebase_information(tablebases: &Tablebases) {
+    println!(
+        "info string found {} tablebase files with up to {} pieces",
+        tablebases.count(),
+        tablebases.max_pieces()
+    );
+    stdout_sync();
 }
 
 pub fn board_best_move(engine: &mut Engine, board: &BoardChain) {
-    let board_info = engine.min_search(board);
+    let board_info = engine.get_lines(board)[0];
 
     if let Some(bestmove) = board_info.peek() {
         let score = score_to_str(board_info.score());
         let depth = board_info.depth();
 
         uci_variable!(bestmove);
+        let pv = engine.get_pv(0);
+        if pv.first() == Some(&bestmove) {
+            if let Some(ponder) = pv.get(1) {
+                uci_variable!(ponder);
+            }
+        }
         uci_token!("info");
         uci_variable!(score);
         uci_variable!(depth);
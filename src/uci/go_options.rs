@@ -1,18 +1,20 @@
 use std::time::Duration;
 
-use chess::{Board, Piece};
+use chess::{Board, ChessMove, Piece};
 use logos::Logos;
 
+use crate::evaluate::Score;
 use crate::search::deadline::Deadline;
 use crate::search::Depth;
+use crate::uci::options::{UCIOptions, PONDER};
 use crate::uci::tokens::UCIGoToken::{self, *};
 
-const BUFFER: Duration = Duration::from_millis(250);
-
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum GoOptions {
     Infinite,
     ToDepth(Depth),
+    Nodes(usize),
+    Mate(Score),
 
     MoveTime(Duration),
 
@@ -21,6 +23,7 @@ pub enum GoOptions {
         black_time: Duration,
         white_inc: Duration,
         black_inc: Duration,
+        moves_to_go: Option<u32>,
     },
 }
 
@@ -32,6 +35,7 @@ impl GoOptions {
         let mut black_time = None;
         let mut white_inc = None;
         let mut black_inc = None;
+        let mut moves_to_go = None;
 
         while let Some(token) = lexer.next() {
             match token {
@@ -64,11 +68,26 @@ impl GoOptions {
                         black_inc = Some(Duration::from_millis(ms));
                     }
                 }
+                Ok(MovesToGo) => {
+                    if let Some(Ok(Number(moves))) = lexer.next() {
+                        moves_to_go = Some(moves.clamp(1, u32::MAX as u64) as u32);
+                    }
+                }
                 Ok(Depth) => {
                     if let Some(Ok(Number(depth))) = lexer.next() {
                         return GoOptions::ToDepth(depth.try_into().unwrap_or(0));
                     }
                 }
+                Ok(Mate) => {
+                    if let Some(Ok(Number(moves))) = lexer.next() {
+                        return GoOptions::Mate(moves.try_into().unwrap_or(Score::MAX));
+                    }
+                }
+                Ok(Nodes) => {
+                    if let Some(Ok(Number(nodes))) = lexer.next() {
+                        return GoOptions::Nodes(nodes.try_into().unwrap_or(usize::MAX));
+                    }
+                }
                 Ok(_) => {}
                 Err(()) => {}
             }
@@ -80,22 +99,49 @@ impl GoOptions {
                 black_time: black_time.unwrap_or(Duration::from_millis(0)),
                 white_inc: white_inc.unwrap_or(Duration::from_millis(0)),
                 black_inc: black_inc.unwrap_or(Duration::from_millis(0)),
+                moves_to_go,
             }
         } else {
             GoOptions::Infinite
         }
     }
 
-    pub fn to_deadline(self, board: &Board) -> Deadline {
+    pub fn search_moves(options: &str, board: &Board) -> Vec<ChessMove> {
+        let mut listing = false;
+        let mut search_moves = Vec::new();
+
+        for token in UCIGoToken::lexer(options) {
+            match token {
+                Ok(SearchMoves) => listing = true,
+                Ok(Move(movement)) if listing => {
+                    if board.legal(movement) {
+                        search_moves.push(movement);
+                    }
+                }
+                _ => listing = false,
+            }
+        }
+
+        search_moves
+    }
+
+    pub fn ponder(options: &str) -> bool {
+        UCIGoToken::lexer(options).any(|token| token == Ok(Ponder))
+    }
+
+    pub fn to_deadline(self, board: &Board, options: &UCIOptions) -> Deadline {
         match self {
             GoOptions::Infinite => Deadline::none(),
             GoOptions::ToDepth(depth) => Deadline::depth(depth),
+            GoOptions::Nodes(nodes) => Deadline::nodes(nodes),
+            GoOptions::Mate(moves) => Deadline::mate(moves),
             GoOptions::MoveTime(time) => Deadline::timeout(time),
             GoOptions::TimeLimit {
                 white_time,
                 black_time,
                 white_inc,
                 black_inc,
+                moves_to_go,
             } => {
                 let side = board.side_to_move();
 
@@ -104,23 +150,37 @@ impl GoOptions {
                     chess::Color::Black => (black_time, black_inc),
                 };
 
-                let moves_left = (48
-                    + board.pieces(Piece::Pawn).popcnt()
-                    + 4 * board.pieces(Piece::Knight).popcnt()
-                    + 4 * board.pieces(Piece::Bishop).popcnt()
-                    + 8 * board.pieces(Piece::Rook).popcnt()
-                    + 16 * board.pieces(Piece::Queen).popcnt())
-                    / 4;
-
+                // The material only estimates how long the game will last,
+                // so it is only used when the clock does not tell us.
+                let moves_left = moves_to_go.unwrap_or_else(|| {
+                    (48 + board.pieces(Piece::Pawn).popcnt()
+                        + 4 * board.pieces(Piece::Knight).popcnt()
+                        + 4 * board.pieces(Piece::Bishop).popcnt()
+                        + 8 * board.pieces(Piece::Rook).popcnt()
+                        + 16 * board.pieces(Piece::Queen).popcnt())
+                        / 4
+                });
+
+                let overhead = options.move_overhead();
+                let available = time.saturating_sub(overhead);
                 let adjusted_inc = inc.checked_div(4).unwrap_or_default();
 
-                let time_for_move = adjusted_inc
-                    + time
-                        .checked_div(moves_left as u32)
-                        .unwrap_or_default()
-                        .saturating_sub(BUFFER);
+                // The overhead is paid once out of the clock, not once for every move left.
+                let share = available / moves_left.max(1);
+                let mut optimum = share + adjusted_inc;
+
+                // Pondering on the opponent's time means the hash is usually
+                // warm when our clock starts, so we can afford to use a bit more.
+                if options.check(&PONDER) {
+                    optimum += optimum / 4;
+                }
+
+                // An unstable search may run past the optimum,
+                // but neither ever spends more than half of what is left.
+                let optimum = optimum.min(available / 2);
+                let maximum = (share * 4 + adjusted_inc).min(available / 2).max(optimum);
 
-                Deadline::timeout(time_for_move)
+                Deadline::time_limit(optimum, maximum)
             }
         }
     }
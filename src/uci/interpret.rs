@@ -1,11 +1,12 @@
 use std::io::{self, BufRead};
-use std::str::FromStr;
 
-use chess::{Board, ChessMove};
+use chess::Board;
 use logos::Logos;
 
 use crate::uci::display::stdout_sync;
 use crate::uci::go_options::GoOptions;
+use crate::uci::options::UCIOptions;
+use crate::uci::position::parse_position;
 use crate::uci::thread::UCIThread;
 use crate::uci::tokens::UCIToken::{self, *};
 
@@ -24,26 +25,46 @@ pub fn uci_loop() {
         while let Some(token) = lexer.next() {
             match token {
                 Ok(FlyingDutchmanTest) => {
-                    tests::play_self::play_self();
+                    let name = lexer.remainder().trim();
+                    for _ in lexer.by_ref() {}
+
+                    match name {
+                        "" => tests::play_self::play_self(),
+                        "bench" => tests::bench::bench(),
+                        _ => eprintln!("unknown test: {}", name),
+                    }
                 }
                 Ok(Uci) => {
                     // Respond to the UCI identification command.
                     println!("id name Flying-Dutchman");
                     println!("id author Sam Belliveau");
+                    thread.options().print();
                     println!("uciok");
                 }
                 Ok(NewGame) => thread.reset(),
                 Ok(IsReady) => {
-                    // Respond to the isready command.
+                    // Respond to the isready command, once the options
+                    // that were set are in place, unless we are searching.
+                    if let Err(err) = thread.apply_options() {
+                        eprintln!("{}", err);
+                    }
                     println!("readyok");
                 }
                 Ok(Go) => {
                     // Start searching for a move.
-                    let info = GoOptions::build(lexer.remainder().trim());
-                    let deadline = info.to_deadline(history.last());
+                    let options = lexer.remainder().trim();
+                    let info = GoOptions::build(options);
+                    let mut deadline = info.to_deadline(history.last(), thread.options());
+                    if GoOptions::ponder(options) {
+                        deadline = deadline.ponder();
+                    }
+                    let search_moves = GoOptions::search_moves(options, history.last());
                     for _ in lexer.by_ref() {}
 
-                    thread.search(&history, deadline);
+                    thread.search(&history, deadline, search_moves);
+                }
+                Ok(PonderHit) => {
+                    thread.ponder_hit();
                 }
                 Ok(Stop) => {
                     thread.stop();
@@ -57,36 +78,25 @@ pub fn uci_loop() {
                     let info = lexer.remainder().trim();
                     for _ in lexer.by_ref() {}
 
-                    if info.starts_with("startpos") {
-                        history = BoardChain::new(Board::default());
-                        if info.starts_with("startpos moves") {
-                            let moves = info.trim_start_matches("startpos moves ");
-                            for chess_move in moves.split_whitespace() {
-                                if let Ok(chess_move) = ChessMove::from_str(chess_move) {
-                                    history = history.take_move(chess_move);
-                                }
-                            }
-                        }
-                    } else if info.starts_with("fen") {
-                        let fen = info.trim_start_matches("fen ");
-                        if let Ok(new_board) = Board::from_str(fen.trim()) {
-                            if new_board == Board::default() {
-                                history = BoardChain::new(new_board);
-                            } else {
-                                history = history.take_board(new_board);
-                            }
-                        }
+                    // A rejected command leaves the previous position in place.
+                    match parse_position(info) {
+                        Ok(position) => history = position,
+                        Err(err) => println!("info string {}", err),
+                    }
+                }
+                Ok(SetOption) => {
+                    let info = lexer.remainder().trim();
+                    for _ in lexer.by_ref() {}
 
-                        for chess_move in fen.split_whitespace() {
-                            if let Ok(chess_move) = ChessMove::from_str(chess_move) {
-                                history = history.take_move(chess_move);
+                    match UCIOptions::parse_setoption(info) {
+                        Some((name, value)) => {
+                            if let Err(err) = thread.set_option(name, value) {
+                                eprintln!("{}", err);
                             }
                         }
+                        None => eprintln!("unknown option: {}", info),
                     }
                 }
-                Ok(SetOption) => {
-                    eprintln!("unknown option: {}", lexer.remainder());
-                }
                 _ => {
                     eprintln!("unknown token: {}", lexer.slice());
                 }
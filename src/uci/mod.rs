@@ -1,5 +1,7 @@
 pub mod display;
 pub mod go_options;
 pub mod interpret;
+pub mod options;
+pub mod position;
 pub mod thread;
-pub mod tokens;
\ No newline at end of file
+pub mod tokens;
@@ -0,0 +1,361 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::evaluate::CENTIPAWN;
+use crate::search::engine::DEFAULT_TABLE_SIZE;
+use crate::search::opponent_model::{OpponentModel, OpponentModelKind, OPPONENT_SHALLOW_DEPTH};
+use crate::search::pruning::Pruning;
+
+pub const MEGABYTE: usize = 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UCIOptionKind {
+    Spin {
+        default: i64,
+        min: i64,
+        max: i64,
+    },
+    Check {
+        default: bool,
+    },
+    Combo {
+        default: &'static str,
+        vars: &'static [&'static str],
+    },
+    String {
+        default: &'static str,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UCIOption {
+    pub name: &'static str,
+    pub kind: UCIOptionKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UCIOptionValue {
+    Spin(i64),
+    Check(bool),
+    Combo(&'static str),
+    String(String),
+}
+
+// How an empty string is written, since an option may not end with the word `default`.
+const EMPTY_STRING: &str = "<empty>";
+
+pub const HASH: UCIOption = UCIOption {
+    name: "Hash",
+    kind: UCIOptionKind::Spin {
+        default: (DEFAULT_TABLE_SIZE / MEGABYTE) as i64,
+        min: 1,
+        max: 1024 * 1024,
+    },
+};
+
+pub const THREADS: UCIOption = UCIOption {
+    name: "Threads",
+    kind: UCIOptionKind::Spin {
+        default: 1,
+        min: 1,
+        max: 1024,
+    },
+};
+
+pub const PONDER: UCIOption = UCIOption {
+    name: "Ponder",
+    kind: UCIOptionKind::Check { default: false },
+};
+
+pub const MULTI_PV: UCIOption = UCIOption {
+    name: "MultiPV",
+    kind: UCIOptionKind::Spin {
+        default: 1,
+        min: 1,
+        max: 256,
+    },
+};
+
+pub const MOVE_OVERHEAD: UCIOption = UCIOption {
+    name: "Move Overhead",
+    kind: UCIOptionKind::Spin {
+        default: 250,
+        min: 0,
+        max: 10000,
+    },
+};
+
+pub const OPPONENT_MODEL: UCIOption = UCIOption {
+    name: "OpponentModel",
+    kind: UCIOptionKind::Combo {
+        default: "Off",
+        vars: &["Off", "TopK", "Weighted"],
+    },
+};
+
+pub const OPPONENT_MODEL_MOVES: UCIOption = UCIOption {
+    name: "OpponentModelMoves",
+    kind: UCIOptionKind::Spin {
+        default: OpponentModel::DEFAULT.moves as i64,
+        min: 1,
+        max: 64,
+    },
+};
+
+pub const OPPONENT_MODEL_DEPTH: UCIOption = UCIOption {
+    name: "OpponentModelDepth",
+    kind: UCIOptionKind::Spin {
+        default: OpponentModel::DEFAULT.depth,
+        min: OPPONENT_SHALLOW_DEPTH + 1,
+        max: 64,
+    },
+};
+
+pub const SYZYGY_PATH: UCIOption = UCIOption {
+    name: "SyzygyPath",
+    kind: UCIOptionKind::String { default: "" },
+};
+
+pub const OPTIONS: &[UCIOption] = &[
+    HASH,
+    THREADS,
+    PONDER,
+    MULTI_PV,
+    MOVE_OVERHEAD,
+    OPPONENT_MODEL,
+    OPPONENT_MODEL_MOVES,
+    OPPONENT_MODEL_DEPTH,
+    SYZYGY_PATH,
+];
+
+pub const REVERSE_FUTILITY_MARGIN: UCIOption = UCIOption {
+    name: "ReverseFutilityMargin",
+    kind: UCIOptionKind::Spin {
+        default: Pruning::DEFAULT.reverse_futility_margin / CENTIPAWN,
+        min: 0,
+        max: 1000,
+    },
+};
+
+pub const FUTILITY_MARGIN: UCIOption = UCIOption {
+    name: "FutilityMargin",
+    kind: UCIOptionKind::Spin {
+        default: Pruning::DEFAULT.futility_margin / CENTIPAWN,
+        min: 0,
+        max: 1000,
+    },
+};
+
+pub const RAZORING_MARGIN: UCIOption = UCIOption {
+    name: "RazoringMargin",
+    kind: UCIOptionKind::Spin {
+        default: Pruning::DEFAULT.razoring_margin / CENTIPAWN,
+        min: 0,
+        max: 2000,
+    },
+};
+
+pub const LATE_MOVE_PRUNING_BASE: UCIOption = UCIOption {
+    name: "LateMovePruningBase",
+    kind: UCIOptionKind::Spin {
+        default: Pruning::DEFAULT.late_move_base as i64,
+        min: 1,
+        max: 256,
+    },
+};
+
+/// Options for tuning the search, which can be set but are not announced to the GUI.
+pub const HIDDEN_OPTIONS: &[UCIOption] = &[
+    REVERSE_FUTILITY_MARGIN,
+    FUTILITY_MARGIN,
+    RAZORING_MARGIN,
+    LATE_MOVE_PRUNING_BASE,
+];
+
+fn registered() -> impl Iterator<Item = &'static UCIOption> {
+    OPTIONS.iter().chain(HIDDEN_OPTIONS)
+}
+
+impl UCIOption {
+    pub fn default_value(&self) -> UCIOptionValue {
+        match self.kind {
+            UCIOptionKind::Spin { default, .. } => UCIOptionValue::Spin(default),
+            UCIOptionKind::Check { default } => UCIOptionValue::Check(default),
+            UCIOptionKind::Combo { default, .. } => UCIOptionValue::Combo(default),
+            UCIOptionKind::String { default } => UCIOptionValue::String(default.to_string()),
+        }
+    }
+
+    pub fn parse_value(&self, value: &str) -> Result<UCIOptionValue, String> {
+        let value = value.trim();
+        match self.kind {
+            UCIOptionKind::Spin { min, max, .. } => match value.parse::<i64>() {
+                Ok(spin) if (min..=max).contains(&spin) => Ok(UCIOptionValue::Spin(spin)),
+                Ok(spin) => Err(format!(
+                    "value {} for {} is outside of [{}, {}]",
+                    spin, self.name, min, max
+                )),
+                Err(_) => Err(format!("invalid value for {}: {}", self.name, value)),
+            },
+            UCIOptionKind::Check { .. } => match value.to_ascii_lowercase().as_str() {
+                "true" => Ok(UCIOptionValue::Check(true)),
+                "false" => Ok(UCIOptionValue::Check(false)),
+                _ => Err(format!("invalid value for {}: {}", self.name, value)),
+            },
+            UCIOptionKind::Combo { vars, .. } => vars
+                .iter()
+                .find(|var| var.eq_ignore_ascii_case(value))
+                .map(|var| UCIOptionValue::Combo(var))
+                .ok_or_else(|| format!("invalid value for {}: {}", self.name, value)),
+            UCIOptionKind::String { .. } if value == EMPTY_STRING => {
+                Ok(UCIOptionValue::String(String::new()))
+            }
+            UCIOptionKind::String { .. } => Ok(UCIOptionValue::String(value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for UCIOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "option name {} ", self.name)?;
+        match self.kind {
+            UCIOptionKind::Spin { default, min, max } => {
+                write!(f, "type spin default {} min {} max {}", default, min, max)
+            }
+            UCIOptionKind::Check { default } => write!(f, "type check default {}", default),
+            UCIOptionKind::Combo { default, vars } => {
+                write!(f, "type combo default {}", default)?;
+                for var in vars {
+                    write!(f, " var {}", var)?;
+                }
+                Ok(())
+            }
+            UCIOptionKind::String { default: "" } => {
+                write!(f, "type string default {}", EMPTY_STRING)
+            }
+            UCIOptionKind::String { default } => write!(f, "type string default {}", default),
+        }
+    }
+}
+
+pub struct UCIOptions {
+    values: Vec<UCIOptionValue>,
+}
+
+impl UCIOptions {
+    pub fn new() -> UCIOptions {
+        UCIOptions {
+            values: registered().map(UCIOption::default_value).collect(),
+        }
+    }
+
+    fn index(option: &UCIOption) -> usize {
+        registered()
+            .position(|registered| registered.name == option.name)
+            .expect("Option is not registered")
+    }
+
+    pub fn find(name: &str) -> Option<UCIOption> {
+        registered()
+            .find(|option| option.name.eq_ignore_ascii_case(name))
+            .copied()
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) -> Result<UCIOption, String> {
+        let option = Self::find(name).ok_or_else(|| format!("unknown option: {}", name))?;
+        self.values[Self::index(&option)] = option.parse_value(value)?;
+        Ok(option)
+    }
+
+    pub fn spin(&self, option: &UCIOption) -> i64 {
+        match &self.values[Self::index(option)] {
+            UCIOptionValue::Spin(value) => *value,
+            _ => panic!("{} is not a spin option", option.name),
+        }
+    }
+
+    pub fn check(&self, option: &UCIOption) -> bool {
+        match &self.values[Self::index(option)] {
+            UCIOptionValue::Check(value) => *value,
+            _ => panic!("{} is not a check option", option.name),
+        }
+    }
+
+    pub fn combo(&self, option: &UCIOption) -> &'static str {
+        match &self.values[Self::index(option)] {
+            UCIOptionValue::Combo(value) => value,
+            _ => panic!("{} is not a combo option", option.name),
+        }
+    }
+
+    pub fn string(&self, option: &UCIOption) -> &str {
+        match &self.values[Self::index(option)] {
+            UCIOptionValue::String(value) => value,
+            _ => panic!("{} is not a string option", option.name),
+        }
+    }
+
+    pub fn table_size(&self) -> usize {
+        self.spin(&HASH) as usize * MEGABYTE
+    }
+
+    pub fn threads(&self) -> usize {
+        self.spin(&THREADS) as usize
+    }
+
+    pub fn multi_pv(&self) -> usize {
+        self.spin(&MULTI_PV) as usize
+    }
+
+    pub fn move_overhead(&self) -> Duration {
+        Duration::from_millis(self.spin(&MOVE_OVERHEAD) as u64)
+    }
+
+    pub fn opponent_model(&self) -> OpponentModel {
+        OpponentModel {
+            kind: match self.combo(&OPPONENT_MODEL) {
+                "TopK" => OpponentModelKind::TopK,
+                "Weighted" => OpponentModelKind::Weighted,
+                _ => OpponentModelKind::Off,
+            },
+            moves: self.spin(&OPPONENT_MODEL_MOVES) as usize,
+            depth: self.spin(&OPPONENT_MODEL_DEPTH),
+        }
+    }
+
+    pub fn syzygy_path(&self) -> &str {
+        self.string(&SYZYGY_PATH)
+    }
+
+    pub fn pruning(&self) -> Pruning {
+        Pruning {
+            reverse_futility_margin: self.spin(&REVERSE_FUTILITY_MARGIN) * CENTIPAWN,
+            futility_margin: self.spin(&FUTILITY_MARGIN) * CENTIPAWN,
+            razoring_margin: self.spin(&RAZORING_MARGIN) * CENTIPAWN,
+            late_move_base: self.spin(&LATE_MOVE_PRUNING_BASE) as usize,
+        }
+    }
+
+    pub fn print(&self) {
+        for option in OPTIONS {
+            println!("{}", option);
+        }
+    }
+
+    /// Splits `name <id> value <x>` into the option name and its value.
+    /// Both the name and the value may contain spaces, e.g. `Move Overhead`.
+    pub fn parse_setoption(line: &str) -> Option<(&str, &str)> {
+        let line = line.trim().strip_prefix("name")?.trim_start();
+
+        match line.find(" value") {
+            Some(index) => Some((line[..index].trim(), line[index + 6..].trim())),
+            None => Some((line.trim(), "")),
+        }
+    }
+}
+
+impl Default for UCIOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,60 @@
+use std::str::FromStr;
+
+use chess::{Board, ChessMove};
+
+use crate::search::board_chain::BoardChain;
+
+// A FEN holds the placement, the side to move, the castling rights,
+// the en passant square, the halfmove clock and the fullmove number.
+const FEN_FIELDS: usize = 6;
+
+/// Parses the arguments of `position [startpos | fen <fen>] [moves <move>...]`
+/// into a new game, or explains why the command was rejected.
+pub fn parse_position(info: &str) -> Result<BoardChain<'static>, String> {
+    let mut tokens = info.split_whitespace().peekable();
+
+    let mut history = match tokens.next() {
+        Some("startpos") => BoardChain::new(Board::default()),
+        Some("fen") => {
+            let mut fields = Vec::new();
+            while let Some(&field) = tokens.peek() {
+                if field == "moves" || fields.len() == FEN_FIELDS {
+                    break;
+                }
+
+                fields.push(field);
+                tokens.next();
+            }
+
+            let fen = fields.join(" ");
+            let board = Board::from_str(&fen).map_err(|_| format!("invalid fen: {}", fen))?;
+
+            // The chess crate ignores the halfmove clock of a FEN.
+            let halfmove_clock = fields
+                .get(4)
+                .and_then(|clock| clock.parse().ok())
+                .unwrap_or(0);
+
+            BoardChain::with_halfmove_clock(board, halfmove_clock)
+        }
+        Some(token) => return Err(format!("expected startpos or fen, found: {}", token)),
+        None => return Err(String::from("expected startpos or fen")),
+    };
+
+    match tokens.next() {
+        Some("moves") => {}
+        Some(token) => return Err(format!("expected moves, found: {}", token)),
+        None => return Ok(history),
+    }
+
+    for token in tokens {
+        let movement = ChessMove::from_str(token)
+            .ok()
+            .filter(|&movement| history.last().legal(movement))
+            .ok_or_else(|| format!("illegal move: {}", token))?;
+
+        history = history.take_move(movement);
+    }
+
+    Ok(history)
+}
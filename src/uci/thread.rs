@@ -1,26 +1,100 @@
 use std::{
     sync::{Arc, Mutex},
     thread,
+    time::Duration,
 };
 
+use chess::ChessMove;
+
 use crate::search::board_chain::BoardChain;
 use crate::search::deadline::Deadline;
 use crate::search::engine::Engine;
+use crate::search::time_manager::TimeManager;
+use crate::syzygy::probe::Tablebases;
+use crate::transposition::table_entry::TTableEntry;
 
 use crate::uci::display;
+use crate::uci::options::{self, UCIOption, UCIOptions};
 
 pub struct UCIThread {
     deadline: Arc<Deadline>,
     engine: Arc<Mutex<Engine>>,
     search_thread: Option<thread::JoinHandle<()>>,
+    options: UCIOptions,
+    // Options that were set while a search held the engine, applied once it is done.
+    pending: Vec<UCIOption>,
 }
 
 impl UCIThread {
     pub fn new() -> UCIThread {
         UCIThread {
             deadline: Arc::new(Deadline::none()),
-            engine: Arc::new(Mutex::new(Engine::new())),
+            // The table is only sized once the GUI had its chance to set the Hash option.
+            engine: Arc::new(Mutex::new(Engine::with_table_size(0))),
             search_thread: None,
+            options: UCIOptions::new(),
+            pending: vec![options::HASH],
+        }
+    }
+
+    pub fn options(&self) -> &UCIOptions {
+        &self.options
+    }
+
+    /// Sets the value of an option. A running search holds the engine until it
+    /// is stopped, so the option only reaches the engine once the search is over.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let option = self.options.set(name, value)?;
+
+        if !self.pending.contains(&option) {
+            self.pending.push(option);
+        }
+
+        self.apply_options()
+    }
+
+    fn is_searching(&self) -> bool {
+        self.search_thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    /// Hands the options that were set since the last call to the engine,
+    /// unless a search is running, in which case they wait for the next call.
+    pub fn apply_options(&mut self) -> Result<(), String> {
+        if self.pending.is_empty() || self.is_searching() {
+            return Ok(());
+        }
+
+        let mut engine = self.engine.lock().map_err(|_| "Engine lock failed")?;
+        for option in std::mem::take(&mut self.pending) {
+            Self::apply_option(&mut engine, &self.options, option);
+        }
+
+        Ok(())
+    }
+
+    fn apply_option(engine: &mut Engine, values: &UCIOptions, option: UCIOption) {
+        if option == options::HASH {
+            engine.set_table_size(values.table_size());
+        } else if option == options::THREADS {
+            engine.set_threads(values.threads());
+        } else if option == options::MULTI_PV {
+            engine.set_multi_pv(values.multi_pv());
+        } else if [
+            options::OPPONENT_MODEL,
+            options::OPPONENT_MODEL_MOVES,
+            options::OPPONENT_MODEL_DEPTH,
+        ]
+        .contains(&option)
+        {
+            engine.set_opponent_model(values.opponent_model());
+        } else if option == options::SYZYGY_PATH {
+            let tablebases = Tablebases::open(values.syzygy_path());
+            display::tablebase_information(&tablebases);
+            engine.set_tablebases(Arc::new(tablebases));
+        } else if options::HIDDEN_OPTIONS.contains(&option) {
+            engine.set_pruning(values.pruning());
         }
     }
 
@@ -32,20 +106,47 @@ impl UCIThread {
         engine: Arc<Mutex<Engine>>,
         history: BoardChain<'static>,
         deadline: Arc<Deadline>,
+        search_moves: Vec<ChessMove>,
         print_result: bool,
     ) -> thread::JoinHandle<()> {
         thread::spawn(move || match engine.lock() {
             Ok(mut engine) => {
                 let start = engine.start_new_search();
+                let mut time_manager = TimeManager::new();
+                engine.set_search_moves(&search_moves);
+                engine.probe_root(&history);
 
                 if print_result {
                     display::board_information(&mut engine, &history, start);
                 }
 
-                while let Ok(..) = engine.iterative_deepening_search(&history, &deadline) {
+                while let Ok(result) = engine.iterative_deepening_search(&history, &deadline) {
+                    // The same depth is searched again until the score lands inside the window.
+                    if let TTableEntry::LowerNode(..) | TTableEntry::UpperNode(..) = result {
+                        if print_result {
+                            display::bound_information(&engine, start, result);
+                        }
+
+                        continue;
+                    }
+
                     if print_result {
                         display::board_information(&mut engine, &history, start);
                     }
+
+                    if !deadline.check_score(result.score()) {
+                        break;
+                    }
+
+                    if !time_manager.check_iteration(&deadline, &result) {
+                        break;
+                    }
+                }
+
+                // A bestmove may not be sent while pondering,
+                // even if the search itself has run out of work.
+                while deadline.is_pondering() && !deadline.passed() {
+                    thread::sleep(Duration::from_millis(1));
                 }
 
                 if print_result {
@@ -59,23 +160,37 @@ impl UCIThread {
         })
     }
 
-    pub fn search<'a>(&mut self, history: &BoardChain<'static>, deadline: Deadline) {
+    pub fn search(
+        &mut self,
+        history: &BoardChain<'static>,
+        deadline: Deadline,
+        search_moves: Vec<ChessMove>,
+    ) {
         if let Some(thread) = self.search_thread.take() {
             if !thread.is_finished() {
                 panic!("Search thread already running");
             }
         }
 
+        if let Err(err) = self.apply_options() {
+            eprintln!("{}", err);
+        }
+
         self.deadline = Arc::new(deadline);
 
         self.search_thread = Some(Self::search_thread(
             Arc::clone(&self.engine),
             history.clone(),
             Arc::clone(&self.deadline),
+            search_moves,
             true,
         ));
     }
 
+    pub fn ponder_hit(&self) {
+        self.deadline.ponder_hit();
+    }
+
     pub fn stop(&mut self) {
         self.deadline.trigger();
 
@@ -85,5 +200,9 @@ impl UCIThread {
                 panic!("Search thread panicked: {:?}", err);
             }
         }
+
+        if let Err(err) = self.apply_options() {
+            eprintln!("{}", err);
+        }
     }
 }
@@ -46,7 +46,7 @@ pub enum UCIGoToken {
     #[regex(r"[0-9]+", |lex| lex.slice().parse().ok())]
     Number(u64),
 
-    #[regex(r"[a-h][1-8][a-h][1-8]+", |lex| ChessMove::from_str(lex.slice()).ok())]
+    #[regex(r"[a-h][1-8][a-h][1-8][qrbn]?", |lex| ChessMove::from_str(lex.slice()).ok())]
     Move(ChessMove),
 
     #[token("searchmoves")]
@@ -81,4 +81,7 @@ pub enum UCIGoToken {
 
     #[token("infinite")]
     Infinite,
+
+    #[token("ponder")]
+    Ponder,
 }